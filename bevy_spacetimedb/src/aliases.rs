@@ -2,7 +2,8 @@ use bevy::prelude::EventReader;
 
 use crate::{
     DeleteEvent, InsertEvent, InsertUpdateEvent, ReducerResultEvent, StdbConnectedEvent,
-    StdbConnectionErrorEvent, StdbDisconnectedEvent, UpdateEvent,
+    StdbConnectionErrorEvent, StdbDisconnectedEvent, StdbReconnectedEvent, StdbReconnectingEvent,
+    UpdateEvent,
 };
 
 /// A type alias for a Bevy event reader for InsertEvent<T>.
@@ -28,3 +29,9 @@ pub type ReadStdbDisconnectedEvent<'w, 's> = EventReader<'w, 's, StdbDisconnecte
 
 /// A type alias for a Bevy event reader for StdbConnectionErrorEvent.
 pub type ReadStdbConnectionErrorEvent<'w, 's> = EventReader<'w, 's, StdbConnectionErrorEvent>;
+
+/// A type alias for a Bevy event reader for StdbReconnectingEvent.
+pub type ReadStdbReconnectingEvent<'w, 's> = EventReader<'w, 's, StdbReconnectingEvent>;
+
+/// A type alias for a Bevy event reader for StdbReconnectedEvent.
+pub type ReadStdbReconnectedEvent<'w, 's> = EventReader<'w, 's, StdbReconnectedEvent>;
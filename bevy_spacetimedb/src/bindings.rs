@@ -0,0 +1,35 @@
+use crate::StdbPlugin;
+use spacetimedb_sdk::__codegen as spacetime_codegen;
+
+/// Trait for registering every table and reducer of the generated bindings at once.
+///
+/// It is implemented for the generated `RemoteModule` by the [`stdb_bindings!`](crate::stdb_bindings) macro.
+pub trait StdbBindings<
+    C: spacetime_codegen::DbConnection<Module = Self> + spacetimedb_sdk::DbContext,
+>: spacetime_codegen::SpacetimeModule<DbConnection = C> where
+    Self: Sized,
+{
+    /// Registers every table of the module with [`TableEvents::all`](crate::TableEvents::all),
+    /// or with [`StdbPlugin::add_table_without_pk`] if it has no primary key,
+    /// and tracks whether their rows are in the client cache with [`StdbPlugin::with_table_ready`].
+    fn add_all_tables(plugin: StdbPlugin<C, Self>) -> StdbPlugin<C, Self>;
+
+    /// Registers the event of every reducer of the module.
+    fn add_all_reducers(plugin: StdbPlugin<C, Self>) -> StdbPlugin<C, Self>;
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C> + StdbBindings<C>,
+> StdbPlugin<C, M>
+{
+    /// Registers every table of the bindings for the bevy application, see [`StdbBindings`].
+    pub fn add_all_tables(self) -> Self {
+        M::add_all_tables(self)
+    }
+
+    /// Registers the event of every reducer of the bindings for the bevy application, see [`StdbBindings`].
+    pub fn add_all_reducers(self) -> Self {
+        M::add_all_reducers(self)
+    }
+}
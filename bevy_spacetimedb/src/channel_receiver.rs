@@ -9,6 +9,10 @@ use std::sync::mpsc::Receiver;
 #[derive(Resource, Deref, DerefMut)]
 struct ChannelReceiver<T>(Mutex<Receiver<T>>);
 
+/// The system set in which the events received through the channels are written.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct EventChannelSet;
+
 /// Allows to register an event channel backed by a `mpsc::Receiver<T>`.
 /// This is useful in multithreaded applications where you want to send events from a different thread
 pub trait AddEventChannelAppExtensions {
@@ -24,7 +28,7 @@ impl AddEventChannelAppExtensions for App {
         );
 
         self.add_event::<T>();
-        self.add_systems(PreUpdate, channel_to_event::<T>);
+        self.add_systems(PreUpdate, channel_to_event::<T>.in_set(EventChannelSet));
         self.insert_resource(ChannelReceiver(Mutex::new(receiver)));
         self
     }
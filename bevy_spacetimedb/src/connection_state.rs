@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use spacetimedb_sdk::Identity;
+
+use crate::{StdbConnectedEvent, StdbConnectionErrorEvent, StdbDisconnectedEvent};
+
+/// The state of the connection to SpacetimeDB, as a Bevy resource.
+///
+/// It is kept up to date with the [`StdbConnectedEvent`], [`StdbDisconnectedEvent`] and [`StdbConnectionErrorEvent`] events,
+/// see [`stdb_connected`] and [`stdb_just_connected`] to use it as a run condition.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub enum StdbConnectionState {
+    /// No connection is open, either because none was requested yet or because it was closed.
+    #[default]
+    Disconnected,
+    /// A connection is being opened.
+    Connecting,
+    /// The connection is established.
+    Connected {
+        /// The `Identity` of the connection.
+        identity: Identity,
+    },
+    /// The connection could not be opened, or was lost because of an error.
+    Errored,
+}
+
+impl StdbConnectionState {
+    /// Returns `true` if the connection is established.
+    pub fn is_connected(&self) -> bool {
+        matches!(self, Self::Connected { .. })
+    }
+
+    /// Returns the `Identity` of the connection if it is established.
+    pub fn identity(&self) -> Option<Identity> {
+        match self {
+            Self::Connected { identity } => Some(*identity),
+            _ => None,
+        }
+    }
+}
+
+/// Updates the [`StdbConnectionState`] from the connection lifecycle events.
+pub(crate) fn update_connection_state(
+    mut state: ResMut<StdbConnectionState>,
+    mut connected: EventReader<StdbConnectedEvent>,
+    mut disconnected: EventReader<StdbDisconnectedEvent>,
+    mut errors: EventReader<StdbConnectionErrorEvent>,
+) {
+    if let Some(event) = connected.read().last() {
+        state.set_if_neq(StdbConnectionState::Connected {
+            identity: event.identity,
+        });
+    }
+    if let Some(event) = disconnected.read().last() {
+        state.set_if_neq(match event.err {
+            Some(_) => StdbConnectionState::Errored,
+            None => StdbConnectionState::Disconnected,
+        });
+    }
+    if errors.read().last().is_some() {
+        state.set_if_neq(StdbConnectionState::Errored);
+    }
+}
+
+/// A run condition that is `true` while the connection to SpacetimeDB is established.
+pub fn stdb_connected() -> impl FnMut(Option<Res<StdbConnectionState>>) -> bool + Clone {
+    |state: Option<Res<StdbConnectionState>>| state.is_some_and(|state| state.is_connected())
+}
+
+/// A run condition that is `true` during the frame in which the connection to SpacetimeDB got established.
+pub fn stdb_just_connected() -> impl FnMut(Option<Res<StdbConnectionState>>) -> bool + Clone {
+    |state: Option<Res<StdbConnectionState>>| {
+        state.is_some_and(|state| state.is_changed() && state.is_connected())
+    }
+}
+
+/// A run condition that is `true` during the frame in which the connection to SpacetimeDB got closed or lost.
+pub fn stdb_just_disconnected() -> impl FnMut(Option<Res<StdbConnectionState>>) -> bool + Clone {
+    |state: Option<Res<StdbConnectionState>>| {
+        state.is_some_and(|state| {
+            state.is_changed()
+                && !state.is_added()
+                && matches!(
+                    *state,
+                    StdbConnectionState::Disconnected | StdbConnectionState::Errored
+                )
+        })
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    StdbConnectEvent, StdbConnectedEvent, StdbConnection, StdbConnectionErrorEvent,
+    StdbConnectionState, StdbDisconnectedEvent, StdbRunMode, transactions::TransactionMessage,
+};
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task, TaskPool, block_on, futures_lite::future},
+};
+use spacetimedb_sdk::{Compression, DbConnectionBuilder, DbContext};
+use std::{
+    marker::PhantomData,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    time::Duration,
+};
+
+/// Installs the SDK callbacks of a registered table on a freshly built connection.
+pub(crate) type TableBinder<C> = Box<dyn Fn(&<C as DbContext>::DbView) + Send + Sync>;
+
+/// Installs the SDK callbacks of a registered reducer on a freshly built connection.
+pub(crate) type ReducerBinder<C> = Box<dyn Fn(&<C as DbContext>::Reducers) + Send + Sync>;
+
+/// Holds everything needed to open a connection, so it can be opened after the plugin is built.
+#[derive(Resource)]
+pub(crate) struct StdbConnector<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+> {
+    pub(crate) module_name: Option<String>,
+    pub(crate) uri: Option<String>,
+    pub(crate) token: Option<String>,
+    pub(crate) run_mode: StdbRunMode<C>,
+    pub(crate) compression: Compression,
+    pub(crate) light_mode: bool,
+    pub(crate) shutdown_timeout: Duration,
+
+    pub(crate) table_binders: Vec<TableBinder<C>>,
+    pub(crate) reducer_binders: Vec<ReducerBinder<C>>,
+
+    pub(crate) send_connected: Sender<StdbConnectedEvent>,
+    pub(crate) send_disconnected: Sender<StdbDisconnectedEvent>,
+    pub(crate) send_connect_error: Sender<StdbConnectionErrorEvent>,
+    /// The transaction channel the end of the messages is committed to, if transaction events are enabled.
+    pub(crate) transaction_sender: Option<Sender<TransactionMessage>>,
+
+    /// The request the last connection was opened with, reused when reconnecting.
+    pub(crate) last_request: Mutex<StdbConnectEvent>,
+
+    pub(crate) _module: PhantomData<fn() -> M>,
+}
+
+/// A connection being built on the IO task pool, see [`start_built_connection`].
+#[derive(Resource)]
+pub(crate) struct PendingConnection<C: Send + 'static> {
+    task: Task<spacetimedb_sdk::Result<C>>,
+    /// Set once the connection has nothing more to deliver, see [`StdbConnection::is_closed`].
+    closed: Arc<AtomicBool>,
+}
+
+impl<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+> StdbConnector<C, M>
+{
+    /// Builds a new connection, registers every table and reducer on it and starts running it.
+    ///
+    /// Values set in `request` take precedence over the ones configured on the plugin.
+    /// If the connection cannot be built, a [`StdbConnectionErrorEvent`] is sent instead.
+    pub(crate) fn connect(&self, request: &StdbConnectEvent) -> Option<StdbConnection<C>> {
+        let (build, closed) = self.connection_builder(request)?;
+        match build() {
+            Ok(conn) => Some(self.start(conn, closed)),
+            Err(err) => {
+                let _ = self
+                    .send_connect_error
+                    .send(StdbConnectionErrorEvent { err });
+                None
+            }
+        }
+    }
+
+    /// Starts building a new connection on the IO task pool, it is started by [`start_built_connection`] once built.
+    ///
+    /// Values set in `request` take precedence over the ones configured on the plugin.
+    pub(crate) fn connect_in_background(
+        &self,
+        request: &StdbConnectEvent,
+    ) -> Option<PendingConnection<C>> {
+        let (build, closed) = self.connection_builder(request)?;
+        // Building the connection waits for the server, it must not block the frame.
+        let task = IoTaskPool::get_or_init(TaskPool::new).spawn(async move { build() });
+        Some(PendingConnection { task, closed })
+    }
+
+    /// Returns the function building the connection along with its closed flag, or `None` if no uri or module name is set.
+    fn connection_builder(
+        &self,
+        request: &StdbConnectEvent,
+    ) -> Option<(
+        impl FnOnce() -> spacetimedb_sdk::Result<C> + Send + 'static,
+        Arc<AtomicBool>,
+    )> {
+        *self.last_request.lock().unwrap() = request.clone();
+
+        let Some(uri) = request.uri.clone().or_else(|| self.uri.clone()) else {
+            error!(
+                "No uri set for StdbPlugin. Set it with the with_uri() function or in the StdbConnectEvent"
+            );
+            return None;
+        };
+        let Some(module_name) = request
+            .module_name
+            .clone()
+            .or_else(|| self.module_name.clone())
+        else {
+            error!(
+                "No module name set for StdbPlugin. Set it with the with_module_name() function or in the StdbConnectEvent"
+            );
+            return None;
+        };
+        let token = request.token.clone().or_else(|| self.token.clone());
+
+        let send_connected = self.send_connected.clone();
+        let send_disconnected = self.send_disconnected.clone();
+        let send_connect_error = self.send_connect_error.clone();
+        let compression = self.compression;
+        let light_mode = self.light_mode;
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let closed_on_error = closed.clone();
+        let closed_on_disconnect = closed.clone();
+
+        let build = move || {
+            DbConnectionBuilder::<M>::new()
+                .with_module_name(module_name)
+                .with_uri(uri)
+                .with_token(token)
+                .with_compression(compression)
+                .with_light_mode(light_mode)
+                .on_connect_error(move |_ctx, err| {
+                    closed_on_error.store(true, Ordering::Release);
+                    send_connect_error
+                        .send(StdbConnectionErrorEvent { err })
+                        .unwrap();
+                })
+                .on_disconnect(move |_ctx, err| {
+                    closed_on_disconnect.store(true, Ordering::Release);
+                    send_disconnected
+                        .send(StdbDisconnectedEvent { err })
+                        .unwrap();
+                })
+                .on_connect(move |_ctx, id, token| {
+                    send_connected
+                        .send(StdbConnectedEvent {
+                            identity: id,
+                            access_token: token.to_string(),
+                        })
+                        .unwrap();
+                })
+                .build()
+        };
+
+        Some((build, closed))
+    }
+
+    /// Registers every table and reducer on a built connection and starts running it.
+    fn start(&self, conn: C, closed: Arc<AtomicBool>) -> StdbConnection<C> {
+        let conn = Arc::new(conn);
+
+        for table_binder in self.table_binders.iter() {
+            table_binder(conn.db());
+        }
+        for reducer_binder in self.reducer_binders.iter() {
+            reducer_binder(conn.reducers());
+        }
+
+        let run_handle = self.run_mode.start(&conn, self.transaction_sender.clone());
+
+        StdbConnection::new(conn)
+            .with_run_handle(run_handle)
+            .with_closed(closed)
+    }
+}
+
+/// Opens a connection whenever a [`StdbConnectEvent`] is received.
+pub(crate) fn connect_on_event<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+>(
+    mut commands: Commands,
+    mut events: EventReader<StdbConnectEvent>,
+    connector: Res<StdbConnector<C, M>>,
+    current: Option<Res<StdbConnection<C>>>,
+    pending: Option<Res<PendingConnection<C>>>,
+    mut state: ResMut<StdbConnectionState>,
+) {
+    // Only the most recent request matters if several were sent during the same frame.
+    let Some(request) = events.read().last() else {
+        return;
+    };
+
+    if current.is_some_and(|conn| conn.is_active()) {
+        warn!("Ignoring StdbConnectEvent, a SpacetimeDB connection is already active");
+        return;
+    }
+    if pending.is_some() {
+        warn!("Ignoring StdbConnectEvent, a SpacetimeDB connection is already being opened");
+        return;
+    }
+
+    match connector.connect_in_background(request) {
+        Some(pending) => {
+            state.set_if_neq(StdbConnectionState::Connecting);
+            commands.insert_resource(pending);
+        }
+        None => {
+            state.set_if_neq(StdbConnectionState::Errored);
+        }
+    }
+}
+
+/// Starts the connection built in the background once it is built, see [`StdbConnector::connect_in_background`].
+pub(crate) fn start_built_connection<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+>(
+    mut commands: Commands,
+    pending: Option<ResMut<PendingConnection<C>>>,
+    connector: Res<StdbConnector<C, M>>,
+) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+    let Some(conn) = block_on(future::poll_once(&mut pending.task)) else {
+        return;
+    };
+
+    commands.remove_resource::<PendingConnection<C>>();
+    match conn {
+        Ok(conn) => commands.insert_resource(connector.start(conn, pending.closed.clone())),
+        Err(err) => {
+            let _ = connector
+                .send_connect_error
+                .send(StdbConnectionErrorEvent { err });
+        }
+    }
+}
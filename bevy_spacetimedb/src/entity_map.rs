@@ -0,0 +1,236 @@
+use std::{hash::Hash, marker::PhantomData, sync::Arc};
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::{schedule::IntoScheduleConfigs, system::SystemParam},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use spacetimedb_sdk::__codegen as spacetime_codegen;
+
+use crate::{DeleteEvent, StdbPlugin};
+
+/// A resource mapping the primary key of the rows of the table `T` to their entity.
+///
+/// It is kept up to date with the entities holding a [`StdbRowKey<T, K>`] component,
+/// and the deleted rows are removed from it, see [`StdbPlugin::add_entity_map`].
+#[derive(Resource, Debug)]
+pub struct StdbEntityMap<T, K: Eq + Hash> {
+    entities: HashMap<K, Entity>,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T, K: Eq + Hash> Default for StdbEntityMap<T, K> {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::default(),
+            _row: PhantomData,
+        }
+    }
+}
+
+impl<T, K: Eq + Hash> StdbEntityMap<T, K> {
+    /// Returns the entity of the row with the given primary key, if any.
+    pub fn get(&self, key: &K) -> Option<Entity> {
+        self.entities.get(key).copied()
+    }
+
+    /// Returns `true` if a row with the given primary key has an entity.
+    pub fn contains(&self, key: &K) -> bool {
+        self.entities.contains_key(key)
+    }
+
+    /// Returns the number of rows with an entity.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if no row has an entity.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Iterates over the primary keys and entities of the rows.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Entity)> {
+        self.entities.iter().map(|(key, entity)| (key, *entity))
+    }
+
+    pub(crate) fn insert(&mut self, key: K, entity: Entity) {
+        self.entities.insert(key, entity);
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) -> Option<Entity> {
+        self.entities.remove(key)
+    }
+
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = (K, Entity)> + '_ {
+        self.entities.drain()
+    }
+
+    /// Removes the key only if it is still mapped to the entity.
+    fn remove_entity(&mut self, key: &K, entity: Entity) {
+        if self.get(key) == Some(entity) {
+            self.entities.remove(key);
+        }
+    }
+}
+
+/// A component linking an entity to the row of the table `T` with the primary key `K`,
+/// the entity is added to the [`StdbEntityMap<T, K>`] while it has this component.
+#[derive(Component, Debug, Clone)]
+#[component(immutable)]
+pub struct StdbRowKey<T: 'static, K: Send + Sync + 'static> {
+    key: K,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static, K: Send + Sync + 'static> StdbRowKey<T, K> {
+    /// Links the entity to the row with the given primary key.
+    pub fn new(key: K) -> Self {
+        Self {
+            key,
+            _row: PhantomData,
+        }
+    }
+
+    /// Returns the primary key of the row.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+/// A [`SystemParam`] to find the entity of the rows of the table `T`.
+///
+/// Unlike `Res<StdbEntityMap<T, K>>`, it does not fail if the map is not registered.
+#[derive(SystemParam)]
+pub struct StdbEntities<'w, T: 'static, K: Eq + Hash + Send + Sync + 'static> {
+    map: Option<Res<'w, StdbEntityMap<T, K>>>,
+}
+
+impl<T: 'static, K: Eq + Hash + Send + Sync + 'static> StdbEntities<'_, T, K> {
+    /// Returns the entity of the row with the given primary key, if any.
+    pub fn get(&self, key: &K) -> Option<Entity> {
+        self.map.as_ref().and_then(|map| map.get(key))
+    }
+
+    /// Returns `true` if a row with the given primary key has an entity.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterates over the primary keys and entities of the rows.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Entity)> {
+        self.map.iter().flat_map(|map| map.iter())
+    }
+}
+
+/// The system set in which the entities of the rows are spawned, updated and removed from the [`StdbEntityMap`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct RowEntitySet;
+
+/// Returns the entity of a row of the table `T` from the [`StdbEntityMap`] of the table, whatever its key.
+#[derive(Resource)]
+pub(crate) struct RowEntityLookup<T>(pub(crate) fn(&World, &T) -> Option<Entity>);
+
+/// Marks the table `T` as mirrored as entities, the mirror removes its deleted rows from the [`StdbEntityMap`] itself.
+#[derive(Resource)]
+pub(crate) struct MirroredRows<T>(pub(crate) PhantomData<fn() -> T>);
+
+/// The function returning the primary key of a row of the table `T`.
+#[derive(Resource)]
+pub(crate) struct RowKey<T, K>(Arc<dyn Fn(&T) -> K + Send + Sync>);
+
+impl<T, K> RowKey<T, K> {
+    pub(crate) fn key(&self, row: &T) -> K {
+        (self.0)(row)
+    }
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Registers the [`StdbEntityMap<TRow, K>`] resource, mapping the primary key returned by `key_fn`
+    /// to the entities holding a [`StdbRowKey<TRow, K>`] component.
+    ///
+    /// The key of a deleted row is removed from the map, the table must be registered with its delete events.
+    pub fn add_entity_map<TRow, K, KF>(mut self, key_fn: KF) -> Self
+    where
+        TRow: Send + Sync + 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        KF: 'static + Send + Sync + Fn(&TRow) -> K,
+    {
+        let key_fn: Arc<dyn Fn(&TRow) -> K + Send + Sync> = Arc::new(key_fn);
+        self.mirror_registers.push(Box::new(move |app: &mut App| {
+            register_entity_map(app, key_fn.clone());
+        }));
+        self
+    }
+}
+
+/// Initializes the [`StdbEntityMap<T, K>`] and the observers and systems keeping it up to date.
+pub(crate) fn register_entity_map<T, K>(app: &mut App, key_fn: Arc<dyn Fn(&T) -> K + Send + Sync>)
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    if app.world().contains_resource::<StdbEntityMap<T, K>>() {
+        return;
+    }
+
+    app.init_resource::<StdbEntityMap<T, K>>()
+        .insert_resource(RowKey(key_fn))
+        .insert_resource(RowEntityLookup(lookup_row_entity::<T, K>))
+        .add_observer(map_row_key::<T, K>)
+        .add_observer(unmap_row_key::<T, K>)
+        .add_systems(
+            PreUpdate,
+            unmap_deleted_rows::<T, K>
+                .in_set(RowEntitySet)
+                .run_if(not(resource_exists::<MirroredRows<T>>)),
+        );
+}
+
+/// Returns the entity mapped to the key of the row, if any.
+fn lookup_row_entity<T, K>(world: &World, row: &T) -> Option<Entity>
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+{
+    let key = world.get_resource::<RowKey<T, K>>()?.key(row);
+    world.get_resource::<StdbEntityMap<T, K>>()?.get(&key)
+}
+
+/// Adds the entity to the map when a [`StdbRowKey`] is inserted.
+fn map_row_key<T: 'static, K: Eq + Hash + Clone + Send + Sync + 'static>(
+    trigger: Trigger<OnInsert, StdbRowKey<T, K>>,
+    keys: Query<&StdbRowKey<T, K>>,
+    mut entities: ResMut<StdbEntityMap<T, K>>,
+) {
+    if let Ok(row_key) = keys.get(trigger.target()) {
+        entities.insert(row_key.key.clone(), trigger.target());
+    }
+}
+
+/// Removes the entity from the map when its [`StdbRowKey`] is replaced, removed or despawned.
+fn unmap_row_key<T: 'static, K: Eq + Hash + Clone + Send + Sync + 'static>(
+    trigger: Trigger<OnReplace, StdbRowKey<T, K>>,
+    keys: Query<&StdbRowKey<T, K>>,
+    mut entities: ResMut<StdbEntityMap<T, K>>,
+) {
+    if let Ok(row_key) = keys.get(trigger.target()) {
+        entities.remove_entity(&row_key.key, trigger.target());
+    }
+}
+
+/// Removes the deleted rows from the map.
+fn unmap_deleted_rows<T: Send + Sync + 'static, K: Eq + Hash + Send + Sync + 'static>(
+    row_key: Res<RowKey<T, K>>,
+    mut entities: ResMut<StdbEntityMap<T, K>>,
+    mut deletes: EventReader<DeleteEvent<T>>,
+) {
+    for event in deletes.read() {
+        entities.remove(&row_key.key(&event.row));
+    }
+}
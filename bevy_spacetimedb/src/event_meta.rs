@@ -0,0 +1,122 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+use spacetimedb_sdk::{
+    __codegen as spacetime_codegen, ConnectionId, DbContext, Event, Identity, Timestamp,
+};
+
+use crate::StdbPlugin;
+
+/// Where the change of a row comes from, carried by the row events
+/// when the plugin is built with [`StdbPlugin::with_event_meta`].
+#[derive(Debug, Clone)]
+pub enum StdbEventMeta {
+    /// The row was received when a subscription was applied, or removed when it was unsubscribed.
+    Subscription,
+    /// The row was changed by a reducer, called by this connection or by another client.
+    Reducer(StdbReducerMeta),
+    /// The row was changed by a transaction without reducer information, e.g. in light mode.
+    Unknown,
+}
+
+impl StdbEventMeta {
+    /// Returns `true` if the row was received from a subscription.
+    pub fn is_subscription(&self) -> bool {
+        matches!(self, Self::Subscription)
+    }
+
+    /// Returns `true` if the row was changed by a reducer called by this connection.
+    pub fn is_own_reducer(&self) -> bool {
+        self.reducer().is_some_and(|reducer| reducer.is_own)
+    }
+
+    /// Returns the reducer call that changed the row, if any.
+    pub fn reducer(&self) -> Option<&StdbReducerMeta> {
+        match self {
+            Self::Reducer(reducer) => Some(reducer),
+            _ => None,
+        }
+    }
+
+    /// Reads the metadata of the event of a callback.
+    pub(crate) fn from_context<Ctx, R>(ctx: &Ctx) -> Self
+    where
+        Ctx: spacetime_codegen::AbstractEventContext<Event = Event<R>>,
+        R: spacetime_codegen::Reducer,
+    {
+        match ctx.event() {
+            Event::SubscribeApplied | Event::UnsubscribeApplied => Self::Subscription,
+            Event::Reducer(event) => Self::Reducer(StdbReducerMeta {
+                name: event.reducer.reducer_name(),
+                caller_identity: event.caller_identity,
+                caller_connection_id: event.caller_connection_id,
+                timestamp: event.timestamp,
+                is_own: event.caller_connection_id == Some(ctx.connection_id()),
+                reducer: Arc::new(Mutex::new(event.reducer.clone())),
+            }),
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The reducer call that changed a row, see [`StdbEventMeta::Reducer`].
+#[derive(Clone)]
+pub struct StdbReducerMeta {
+    /// The name of the reducer.
+    pub name: &'static str,
+    /// The `Identity` of the client that called the reducer.
+    pub caller_identity: Identity,
+    /// The `ConnectionId` of the client that called the reducer, if any.
+    pub caller_connection_id: Option<ConnectionId>,
+    /// The time at which the reducer was invoked.
+    pub timestamp: Timestamp,
+    /// Whether the reducer was called by this connection.
+    pub is_own: bool,
+    /// The `Reducer` variant with the arguments of the call.
+    reducer: Arc<dyn Any + Send + Sync>,
+}
+
+impl StdbReducerMeta {
+    /// Returns the `Reducer` variant of the call, with its arguments, if `R` is the `Reducer` enum of the bindings.
+    pub fn reducer<R: Clone + 'static>(&self) -> Option<R> {
+        let reducer = self.reducer.downcast_ref::<Mutex<R>>()?;
+        Some(reducer.lock().unwrap().clone())
+    }
+}
+
+impl std::fmt::Debug for StdbReducerMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdbReducerMeta")
+            .field("name", &self.name)
+            .field("caller_identity", &self.caller_identity)
+            .field("caller_connection_id", &self.caller_connection_id)
+            .field("timestamp", &self.timestamp)
+            .field("is_own", &self.is_own)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns `true` if the event of a callback is the application of a subscription.
+pub(crate) fn is_subscription_applied<Ctx, R>(ctx: &Ctx) -> bool
+where
+    Ctx: spacetime_codegen::AbstractEventContext<Event = Event<R>>,
+    R: spacetime_codegen::Reducer,
+{
+    matches!(ctx.event(), Event::SubscribeApplied)
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Sets whether the row events carry a [`StdbEventMeta`], telling where the change of the row comes from.
+    ///
+    /// It is disabled by default, as the reducer arguments are cloned for each row changed by a reducer.
+    pub fn with_event_meta(mut self, event_meta: bool) -> Self {
+        self.event_meta = event_meta;
+        self
+    }
+}
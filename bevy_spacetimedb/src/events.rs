@@ -1,6 +1,24 @@
+use std::time::Duration;
+
 use bevy::prelude::Event;
 use spacetimedb_sdk::{Error, Identity};
 
+use crate::{StdbEventMeta, StdbSubscriptionId};
+
+/// An event that opens the connection to SpacetimeDB when the plugin is built with
+/// [`StdbPlugin::with_delayed_connect`](crate::StdbPlugin::with_delayed_connect).
+///
+/// Fields left as `None` fall back to the values configured on the plugin.
+#[derive(Event, Default, Debug, Clone)]
+pub struct StdbConnectEvent {
+    /// The URI of the SpacetimeDB host which is running the remote module.
+    pub uri: Option<String>,
+    /// The name or identity of the remote module.
+    pub module_name: Option<String>,
+    /// The token with which to authenticate with the remote database.
+    pub token: Option<String>,
+}
+
 /// An event that is triggered when a connection to SpacetimeDB is established.
 #[derive(Event)]
 pub struct StdbConnectedEvent {
@@ -24,27 +42,51 @@ pub struct StdbConnectionErrorEvent {
     pub err: Error,
 }
 
+/// An event that is triggered when a reconnection attempt to SpacetimeDB is scheduled.
+#[derive(Event, Debug)]
+pub struct StdbReconnectingEvent {
+    /// The number of the scheduled attempt, starting at 1.
+    pub attempt: u32,
+    /// The delay after which the attempt will be made.
+    pub delay: Duration,
+}
+
+/// An event that is triggered when the connection to SpacetimeDB has been re-established.
+#[derive(Event, Debug)]
+pub struct StdbReconnectedEvent {
+    /// The number of attempts it took to reconnect.
+    pub attempts: u32,
+}
+
 /// An event that is triggered when a row is inserted into a table.
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct InsertEvent<T> {
     /// The row that was inserted.
     pub row: T,
+    /// Whether the row was already in the table, and was received when a subscription was applied.
+    pub initial: bool,
+    /// Where the insertion comes from, if enabled with [`StdbPlugin::with_event_meta`](crate::StdbPlugin::with_event_meta).
+    pub meta: Option<StdbEventMeta>,
 }
 
 /// An event that is triggered when a row is deleted from a table.
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct DeleteEvent<T> {
     /// The row that was deleted.
     pub row: T,
+    /// Where the deletion comes from, if enabled with [`StdbPlugin::with_event_meta`](crate::StdbPlugin::with_event_meta).
+    pub meta: Option<StdbEventMeta>,
 }
 
 /// An event that is triggered when a row is updated in a table.
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct UpdateEvent<T> {
     /// The old row.
     pub old: T,
     /// The new row.
     pub new: T,
+    /// Where the update comes from, if enabled with [`StdbPlugin::with_event_meta`](crate::StdbPlugin::with_event_meta).
+    pub meta: Option<StdbEventMeta>,
 }
 
 /// An event that is triggered when a row is inserted or updated in a table.
@@ -54,6 +96,10 @@ pub struct InsertUpdateEvent<T> {
     pub old: Option<T>,
     /// The new value of the row or the inserted value.
     pub new: T,
+    /// Whether the row was already in the table, and was received when a subscription was applied.
+    pub initial: bool,
+    /// Where the change comes from, if enabled with [`StdbPlugin::with_event_meta`](crate::StdbPlugin::with_event_meta).
+    pub meta: Option<StdbEventMeta>,
 }
 
 /// An event that is triggered when a reducer is invoked.
@@ -69,3 +115,19 @@ impl<T> ReducerResultEvent<T> {
         Self { result }
     }
 }
+
+/// An event that is sent when a subscription managed by [`StdbSubscriptions`](crate::StdbSubscriptions) is applied.
+#[derive(Event, Debug)]
+pub struct SubscriptionAppliedEvent {
+    /// The subscription that was applied.
+    pub id: StdbSubscriptionId,
+}
+
+/// An event that is sent when a subscription managed by [`StdbSubscriptions`](crate::StdbSubscriptions) fails.
+#[derive(Event, Debug)]
+pub struct SubscriptionErrorEvent {
+    /// The subscription that failed.
+    pub id: StdbSubscriptionId,
+    /// The error that occurred.
+    pub err: Error,
+}
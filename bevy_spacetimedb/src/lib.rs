@@ -3,18 +3,56 @@
 //! A bevy plugin for SpacetimeDB.
 
 mod aliases;
+mod bindings;
 mod channel_receiver;
+mod connection_state;
+mod connector;
+mod entity_map;
+mod event_meta;
 mod events;
+mod mirror;
 mod plugin;
+mod query;
+mod reconnect;
 mod reducers;
+mod row_triggers;
+mod run_mode;
+mod shutdown;
 mod stdb_connection;
+mod stdb_state;
+mod subscription_entity;
+mod subscriptions;
+mod table_ready;
 mod tables;
+mod transactions;
 
 pub use aliases::*;
+/// Used by the code generated by the derive macros, so that it does not depend on how bevy is imported.
+#[doc(hidden)]
+pub use bevy;
 pub use bevy_spacetimedb_macros::*;
+pub use bindings::StdbBindings;
 pub use channel_receiver::AddEventChannelAppExtensions;
+pub use connection_state::{
+    StdbConnectionState, stdb_connected, stdb_just_connected, stdb_just_disconnected,
+};
+pub use entity_map::{StdbEntities, StdbEntityMap, StdbRowKey};
+pub use event_meta::{StdbEventMeta, StdbReducerMeta};
 pub use events::*;
+pub use mirror::{StdbRow, StdbRowBundle};
 pub use plugin::*;
+pub use query::{
+    StdbColumn, StdbFilter, StdbQuery, StdbQueryError, StdbSqlValue, StdbSubscriptionQuery,
+    StdbTableRow, Subscribe,
+};
+pub use reconnect::ReconnectPolicy;
 pub use reducers::RegisterableReducerEvent;
+pub use row_triggers::RowEventDelivery;
+pub use run_mode::StdbRunMode;
 pub use stdb_connection::*;
-pub use tables::TableEvents;
+pub use stdb_state::StdbState;
+pub use subscription_entity::StdbSubscription;
+pub use subscriptions::{StdbSubscriptionId, StdbSubscriptionState, StdbSubscriptions};
+pub use table_ready::{StdbTableReady, StdbTableSynced, stdb_table_ready};
+pub use tables::{TableAccessor, TableEvents};
+pub use transactions::{RowChangeKind, StdbRowChange, StdbTransactionEvent};
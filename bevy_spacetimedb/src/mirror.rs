@@ -0,0 +1,169 @@
+use std::{any::TypeId, hash::Hash, marker::PhantomData, sync::Arc};
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::schedule::IntoScheduleConfigs,
+    prelude::*,
+};
+use spacetimedb_sdk::{__codegen as spacetime_codegen, Table, TableWithPrimaryKey};
+
+use crate::{
+    StdbDisconnectedEvent, StdbEntityMap, StdbPlugin, StdbRowKey, TableAccessor, TableEvents,
+    entity_map::{MirroredRows, RowEntitySet, RowKey, register_entity_map},
+};
+
+/// A component holding a row of the table `T`, spawned by [`StdbPlugin::add_table_as_component`].
+///
+/// It is kept up to date with the table, and the entity is despawned when the row is deleted.
+#[derive(Component, Deref, Debug, Clone)]
+pub struct StdbRow<T: Send + Sync + 'static>(pub T);
+
+/// Trait for turning the rows of a table into the components of their entity,
+/// used by [`StdbPlugin::add_table_as_bundle`] to mirror a table.
+///
+/// It can be derived for a struct also deriving `Bundle`, see the `StdbRowBundle` derive macro.
+pub trait StdbRowBundle: Send + Sync + 'static {
+    /// The row of the table.
+    type Row: Send + Sync + Clone + 'static;
+
+    /// Returns the components of the entity of a row.
+    fn spawn_bundle(row: &Self::Row) -> impl Bundle;
+
+    /// Updates the components of the entity of a row when the row is updated.
+    fn apply_update(old: &Self::Row, new: &Self::Row, entity: EntityCommands);
+}
+
+impl<T: Send + Sync + Clone + 'static> StdbRowBundle for StdbRow<T> {
+    type Row = T;
+
+    fn spawn_bundle(row: &T) -> impl Bundle {
+        StdbRow(row.clone())
+    }
+
+    fn apply_update(_old: &T, new: &T, mut entity: EntityCommands) {
+        entity.insert(StdbRow(new.clone()));
+    }
+}
+
+/// A row change of a mirrored table, the changes of a table are all sent through the same event to keep their order.
+#[derive(Event)]
+pub(crate) enum RowMirrorEvent<T> {
+    Insert(T),
+    Update { old: T, new: T },
+    Delete(T),
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Registers a table for the bevy application, and mirrors each of its rows as an entity with a [`StdbRow`] component.
+    ///
+    /// Entities are spawned when a row is inserted, updated along with it, and despawned when it is deleted.
+    /// The `key_fn` returns the primary key of a row, the entity of a row can be found with the [`StdbEntityMap`] resource.
+    /// The entities are despawned when the connection is lost, and spawned again from the rows of the next connection.
+    pub fn add_table_as_component<TRow, K, F, KF>(self, accessor: F, key_fn: KF) -> Self
+    where
+        TRow: Send + Sync + Clone + 'static,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        KF: 'static + Send + Sync + Fn(&TRow) -> K,
+    {
+        self.add_table_as_bundle::<StdbRow<TRow>, F, K>(accessor, key_fn)
+    }
+
+    /// Registers a table for the bevy application, and mirrors each of its rows as an entity with the components of `B`.
+    ///
+    /// Entities are spawned when a row is inserted, updated with [`StdbRowBundle::apply_update`], and despawned when it is deleted.
+    /// The `key_fn` returns the primary key of a row, the entity of a row can be found with the [`StdbEntityMap`] resource.
+    /// The entities are despawned when the connection is lost, and spawned again from the rows of the next connection.
+    ///
+    /// The table can also be registered explicitly, e.g. with [`StdbPlugin::add_all_tables`], without it being reported as registered twice.
+    pub fn add_table_as_bundle<B, F, K>(
+        mut self,
+        accessor: F,
+        key_fn: impl Fn(&B::Row) -> K + Send + Sync + 'static,
+    ) -> Self
+    where
+        B: StdbRowBundle,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = B::Row, EventContext = M::EventContext> + TableWithPrimaryKey<Row = B::Row>,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        let key_fn = Arc::new(key_fn);
+        self.mirrored_rows.push(TypeId::of::<B::Row>());
+        self.mirror_registers.push(Box::new(move |app: &mut App| {
+            register_entity_map(app, key_fn.clone());
+            app.insert_resource(MirroredRows::<B::Row>(PhantomData))
+                .add_systems(PreUpdate, mirror_rows::<B, K>.in_set(RowEntitySet));
+        }));
+
+        // The mirror is bound along with the other events of the table, which may also be registered explicitly.
+        self.register_partial_table(accessor, TableEvents::all(), false)
+    }
+}
+
+/// Spawns, updates and despawns the entities of the rows of the table of `B`.
+fn mirror_rows<B: StdbRowBundle, K: Eq + Hash + Clone + Send + Sync + 'static>(
+    mut commands: Commands,
+    row_key: Res<RowKey<B::Row, K>>,
+    mut entities: ResMut<StdbEntityMap<B::Row, K>>,
+    mut changes: EventReader<RowMirrorEvent<B::Row>>,
+    mut disconnected: EventReader<StdbDisconnectedEvent>,
+) {
+    // In the order they were received, so that a row deleted and inserted again keeps an entity.
+    for change in changes.read() {
+        match change {
+            RowMirrorEvent::Insert(row) => {
+                let key = row_key.key(row);
+                match entities.get(&key) {
+                    // A row inserted again, e.g. by another subscription, replaces the components of the existing entity.
+                    Some(entity) => {
+                        commands.entity(entity).insert(B::spawn_bundle(row));
+                    }
+                    None => spawn_row::<B, K>(&mut commands, &mut entities, key, row),
+                }
+            }
+            RowMirrorEvent::Update { old, new } => {
+                let key = row_key.key(new);
+                match entities.get(&key) {
+                    Some(entity) => B::apply_update(old, new, commands.entity(entity)),
+                    None => spawn_row::<B, K>(&mut commands, &mut entities, key, new),
+                }
+            }
+            RowMirrorEvent::Delete(row) => {
+                if let Some(entity) = entities.remove(&row_key.key(row)) {
+                    commands.entity(entity).try_despawn();
+                }
+            }
+        }
+    }
+
+    // The rows deleted while disconnected are never received, so the rows of the next connection start from scratch.
+    if disconnected.read().count() > 0 {
+        for (_, entity) in entities.drain() {
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
+
+/// Spawns the entity of a row.
+fn spawn_row<B: StdbRowBundle, K: Eq + Hash + Clone + Send + Sync + 'static>(
+    commands: &mut Commands,
+    entities: &mut StdbEntityMap<B::Row, K>,
+    key: K,
+    row: &B::Row,
+) {
+    // Mapped right away, in case the row is inserted again before the commands are applied.
+    let entity = commands
+        .spawn((
+            B::spawn_bundle(row),
+            StdbRowKey::<B::Row, K>::new(key.clone()),
+        ))
+        .id();
+    entities.insert(key, entity);
+}
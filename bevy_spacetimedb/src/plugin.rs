@@ -1,16 +1,36 @@
 use crate::{
-    AddEventChannelAppExtensions, StdbConnectedEvent, StdbConnection, StdbConnectionErrorEvent,
-    StdbDisconnectedEvent,
+    AddEventChannelAppExtensions, StdbConnectEvent, StdbConnectedEvent, StdbConnectionErrorEvent,
+    StdbConnectionState, StdbDisconnectedEvent, StdbReconnectedEvent, StdbReconnectingEvent,
+    channel_receiver::EventChannelSet,
+    connection_state::update_connection_state,
+    connector::{ReducerBinder, StdbConnector, connect_on_event, start_built_connection},
+    entity_map::RowEntitySet,
+    reconnect::{ReconnectPolicy, ReconnectState, attempt_reconnect, schedule_reconnect},
+    row_triggers::RowEventDelivery,
+    run_mode::{StdbRunMode, frame_tick},
+    shutdown::shutdown_on_exit,
+    stdb_state::{StdbState, register_state},
+    subscriptions::{SubscriptionQueries, register_subscriptions},
+    tables::TableRegistration,
+    transactions::TransactionMessage,
 };
 use bevy::{
-    app::{App, Plugin},
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{schedule::IntoScheduleConfigs, world::FromWorld},
+    log::warn,
     platform::collections::HashMap,
+    state::state::FreelyMutableState,
 };
-use spacetimedb_sdk::{Compression, DbConnectionBuilder, DbContext};
+use spacetimedb_sdk::{Compression, DbContext};
 use std::{
     any::{Any, TypeId},
-    sync::{Mutex, mpsc::channel},
+    marker::PhantomData,
+    sync::{
+        Mutex,
+        mpsc::{Sender, channel},
+    },
     thread::JoinHandle,
+    time::Duration,
 };
 
 /// The plugin for connecting SpacetimeDB with your bevy application.
@@ -21,19 +41,30 @@ pub struct StdbPlugin<
     module_name: Option<String>,
     uri: Option<String>,
     token: Option<String>,
-    run_fn: Option<fn(&C) -> JoinHandle<()>>,
+    pub(crate) run_mode: Option<StdbRunMode<C>>,
     compression: Option<Compression>,
     light_mode: bool,
+    delayed_connect: bool,
+    reconnect: Option<ReconnectPolicy>,
+    state_registers: Vec<fn(&mut App)>,
+    pub(crate) table_ready_registers: Vec<fn(&mut App)>,
+    pub(crate) subscriptions: Vec<SubscriptionQueries>,
+    shutdown_timeout: Duration,
+    pub(crate) row_event_delivery: RowEventDelivery,
+    pub(crate) transaction_events: bool,
+    pub(crate) event_meta: bool,
 
     // Stores Senders for registered table events.
     pub(crate) event_senders: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    // Stores the Sender of the transaction channel, if transaction events are enabled.
+    pub(crate) transaction_sender: Mutex<Option<Sender<TransactionMessage>>>,
+    pub(crate) table_registers: Vec<TableRegistration<C, M>>,
     #[allow(clippy::type_complexity)]
-    pub(crate) table_registers: Vec<
-        Box<dyn Fn(&StdbPlugin<C, M>, &mut App, &'static <C as DbContext>::DbView) + Send + Sync>,
-    >,
+    pub(crate) reducer_registers: Vec<Box<dyn Fn(&mut App) -> ReducerBinder<C> + Send + Sync>>,
     #[allow(clippy::type_complexity)]
-    pub(crate) reducer_registers:
-        Vec<Box<dyn Fn(&mut App, &<C as DbContext>::Reducers) + Send + Sync>>,
+    pub(crate) mirror_registers: Vec<Box<dyn Fn(&mut App) + Send + Sync>>,
+    // Stores the rows of the tables mirrored as entities, their row changes are also sent in the order they are received.
+    pub(crate) mirrored_rows: Vec<TypeId>,
 }
 
 impl<
@@ -46,13 +77,25 @@ impl<
             module_name: Default::default(),
             uri: None,
             token: None,
-            run_fn: None,
+            run_mode: None,
             compression: Some(Compression::default()),
             light_mode: false,
+            delayed_connect: false,
+            reconnect: None,
+            state_registers: Vec::default(),
+            table_ready_registers: Vec::default(),
+            subscriptions: Vec::default(),
+            shutdown_timeout: Duration::from_secs(1),
+            row_event_delivery: RowEventDelivery::default(),
+            transaction_events: false,
+            event_meta: false,
 
             event_senders: Mutex::default(),
+            transaction_sender: Mutex::default(),
             table_registers: Vec::default(),
             reducer_registers: Vec::default(),
+            mirror_registers: Vec::default(),
+            mirrored_rows: Vec::default(),
         }
     }
 }
@@ -65,8 +108,18 @@ impl<
     /// The function that the connection will run with. The recommended function is `DbConnection::run_threaded`.
     ///
     /// Other function are not tested, they may not work.
+    /// This is a shorthand for [`Self::with_run_mode`] with [`StdbRunMode::Threaded`].
     pub fn with_run_fn(mut self, run_fn: fn(&C) -> JoinHandle<()>) -> Self {
-        self.run_fn = Some(run_fn);
+        self.run_mode = Some(StdbRunMode::Threaded(run_fn));
+        self
+    }
+
+    /// Sets how the connection processes the messages it receives.
+    ///
+    /// Use `StdbRunMode::FrameTick(DbConnection::frame_tick)` to process them once per frame on the main schedule,
+    /// instead of on a dedicated thread.
+    pub fn with_run_mode(mut self, run_mode: StdbRunMode<C>) -> Self {
+        self.run_mode = Some(run_mode);
         self
     }
 
@@ -121,20 +174,81 @@ impl<
         self.light_mode = light_mode;
         self
     }
+
+    /// Sets whether opening the connection is delayed until a [`StdbConnectEvent`] is sent.
+    ///
+    /// When enabled, building the plugin only registers the tables, reducers and events.
+    /// The URI, module name and token can then be supplied later through the [`StdbConnectEvent`],
+    /// for example once a login screen has collected them.
+    /// The connection is built in the background, and the [`StdbConnection`](crate::StdbConnection) resource
+    /// is only inserted once it has been built, so systems using it should take it as an `Option<Res<StdbConnection<_>>>`.
+    pub fn with_delayed_connect(mut self, delayed_connect: bool) -> Self {
+        self.delayed_connect = delayed_connect;
+        self
+    }
+
+    /// Re-establishes the connection according to `policy` when it is lost.
+    ///
+    /// All the registered tables and reducers are registered again on the new connection,
+    /// and the [`StdbConnection`](crate::StdbConnection) resource is replaced by it.
+    /// The progress is reported with the [`StdbReconnectingEvent`] and [`StdbReconnectedEvent`] events.
+    /// Disconnecting with [`StdbConnection::disconnect`](crate::StdbConnection::disconnect) does not trigger a reconnection.
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Sets how long to wait for the connection thread to finish when the app exits.
+    ///
+    /// On [`AppExit`](bevy::app::AppExit), the connection is closed and a final [`StdbDisconnectedEvent`] is sent if it was active,
+    /// so that the `on_disconnect` reducers of the module run reliably. Defaults to one second.
+    ///
+    /// The frame handling the exit blocks until the thread or task running the connection finishes, for up to the timeout.
+    /// Nothing is waited for with [`StdbRunMode::FrameTick`](crate::StdbRunMode::FrameTick), which runs on the main schedule.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Drives the state `S` through [`NextState`](bevy::prelude::NextState) as the connection is opened, closed or lost.
+    ///
+    /// Use [`StdbState`] to get the built-in states, or implement `From<StdbState>` for your own states.
+    /// The state is initialized if it was not already, so `OnEnter(StdbState::Connected)`
+    /// can be used to run the setup needed once connected.
+    pub fn with_state<S: FreelyMutableState + FromWorld + From<StdbState>>(mut self) -> Self {
+        self.state_registers.push(register_state::<S>);
+        self
+    }
 }
 
 impl<
-    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Sync,
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M>
+        + DbContext<SubscriptionBuilder = spacetimedb_sdk::__codegen::SubscriptionBuilder<M>>
+        + Send
+        + Sync
+        + 'static,
     M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
 > Plugin for StdbPlugin<C, M>
 {
     fn build(&self, app: &mut App) {
-        self.uri
-            .clone()
-            .expect("No uri set for StdbPlugin. Set it with the with_uri() function");
-        self.module_name.clone().expect(
-            "No module name set for StdbPlugin. Set it with the with_module_name() function",
+        if !self.delayed_connect {
+            self.uri
+                .clone()
+                .expect("No uri set for StdbPlugin. Set it with the with_uri() function");
+            self.module_name.clone().expect(
+                "No module name set for StdbPlugin. Set it with the with_module_name() function",
+            );
+        }
+        let run_mode = self.run_mode.expect(
+            "No run function specified! Set it with the with_run_fn() or with_run_mode() function",
         );
+        if self.transaction_events && !run_mode.commits() {
+            warn!(
+                "Transaction events are enabled, but the run mode does not know where the messages of the server end. \
+                The row events are delivered each frame instead, use StdbRunMode::FrameTick, StdbRunMode::Messages \
+                or StdbRunMode::AsyncMessages to deliver them by transaction"
+            );
+        }
 
         let (send_connected, recv_connected) = channel::<StdbConnectedEvent>();
         let (send_disconnected, recv_disconnected) = channel::<StdbDisconnectedEvent>();
@@ -143,49 +257,89 @@ impl<
             .add_event_channel::<StdbConnectedEvent>(recv_connected)
             .add_event_channel::<StdbDisconnectedEvent>(recv_disconnected);
 
-        // FIXME App should not crash if intial connection fails.
-        let conn = DbConnectionBuilder::<M>::new()
-            .with_module_name(self.module_name.clone().unwrap())
-            .with_uri(self.uri.clone().unwrap())
-            .with_token(self.token.clone())
-            .with_compression(self.compression.unwrap_or_default())
-            .with_light_mode(self.light_mode)
-            .on_connect_error(move |_ctx, err| {
-                send_connect_error
-                    .send(StdbConnectionErrorEvent { err })
-                    .unwrap();
-            })
-            .on_disconnect(move |_ctx, err| {
-                send_disconnected
-                    .send(StdbDisconnectedEvent { err })
-                    .unwrap();
-            })
-            .on_connect(move |_ctx, id, token| {
-                send_connected
-                    .send(StdbConnectedEvent {
-                        identity: id,
-                        access_token: token.to_string(),
-                    })
-                    .unwrap();
-            })
-            .build()
-            .expect("Failed to build connection");
-
-        // A 'static ref is needed for the connection the register tables and reducers
-        // This is fine because only a small and fixed amount of memory will be leaked
-        // Because conn has to live until the end of the program anyways, not using it would not make for any performance improvements.
-        let conn = Box::<C>::leak(Box::new(conn));
-
-        for table_register in self.table_registers.iter() {
-            table_register(self, app, conn.db());
+        // Events are registered right away, the SDK callbacks are bound once a connection is built.
+        for table in self
+            .table_registers
+            .iter()
+            .filter(|table| table.registrations > 1)
+        {
+            warn!(
+                "The table {} was registered {} times, it is only registered once with {:?}",
+                table.name, table.registrations, table.events
+            );
+        }
+        let table_binders = self
+            .table_registers
+            .iter()
+            .map(|table| (table.register)(self, app, table.events))
+            .collect();
+        let reducer_binders = self
+            .reducer_registers
+            .iter()
+            .map(|reducer_register| reducer_register(app))
+            .collect();
+
+        let connector = StdbConnector::<C, M> {
+            module_name: self.module_name.clone(),
+            uri: self.uri.clone(),
+            token: self.token.clone(),
+            run_mode,
+            compression: self.compression.unwrap_or_default(),
+            light_mode: self.light_mode,
+            shutdown_timeout: self.shutdown_timeout,
+            table_binders,
+            reducer_binders,
+            send_connected,
+            send_disconnected,
+            send_connect_error,
+            transaction_sender: self.transaction_sender(app),
+            last_request: Mutex::default(),
+            _module: PhantomData,
+        };
+
+        app.init_resource::<StdbConnectionState>();
+        if !self.delayed_connect {
+            app.insert_resource(StdbConnectionState::Connecting);
+            if let Some(conn) = connector.connect(&StdbConnectEvent::default()) {
+                app.insert_resource(conn);
+            }
+        }
+
+        app.add_event::<StdbConnectEvent>()
+            .add_systems(
+                PreUpdate,
+                (connect_on_event::<C, M>, start_built_connection::<C, M>).chain(),
+            )
+            .add_systems(PreUpdate, update_connection_state.after(EventChannelSet))
+            .add_systems(PreUpdate, frame_tick::<C, M>.before(EventChannelSet))
+            .configure_sets(PreUpdate, RowEntitySet.after(EventChannelSet))
+            .add_systems(Last, shutdown_on_exit::<C, M>)
+            .insert_resource(connector);
+
+        for state_register in self.state_registers.iter() {
+            state_register(app);
         }
-        for reducer_register in self.reducer_registers.iter() {
-            reducer_register(app, conn.reducers());
+
+        register_subscriptions(self, app);
+
+        for table_ready_register in self.table_ready_registers.iter() {
+            table_ready_register(app);
         }
 
-        let run_fn = self.run_fn.expect("No run function specified!");
-        run_fn(conn);
+        for mirror_register in self.mirror_registers.iter() {
+            mirror_register(app);
+        }
 
-        app.insert_resource(StdbConnection::new(conn));
+        if let Some(policy) = self.reconnect.clone() {
+            app.add_event::<StdbReconnectingEvent>()
+                .add_event::<StdbReconnectedEvent>()
+                .insert_resource(ReconnectState::new(policy))
+                .add_systems(
+                    PreUpdate,
+                    (schedule_reconnect, attempt_reconnect::<C, M>)
+                        .chain()
+                        .after(EventChannelSet),
+                );
+        }
     }
 }
@@ -0,0 +1,397 @@
+use std::marker::PhantomData;
+
+use spacetimedb_sdk::{ConnectionId, Identity};
+
+/// A row type of the generated bindings, along with the name of its table and its columns.
+///
+/// It is implemented for the rows of the bindings by the [`stdb_bindings!`](crate::stdb_bindings) macro,
+/// which generates a `<Row>Columns` struct with a [`StdbColumn`] for each field of the row.
+pub trait StdbTableRow: Sized + 'static {
+    /// The name of the table in the database.
+    const TABLE_NAME: &'static str;
+
+    /// The columns of the table.
+    type Columns: 'static;
+
+    /// The columns of the table, passed to the closure of [`StdbQuery::filter`].
+    const COLUMNS: Self::Columns;
+}
+
+/// Builds typed subscription queries on the tables of the generated bindings.
+///
+/// ```ignore
+/// Subscribe::table::<Player>().filter(|player| player.online.eq(true))
+/// ```
+pub struct Subscribe;
+
+impl Subscribe {
+    /// Returns a query selecting every row of the table of `R`.
+    pub fn table<R: StdbTableRow>() -> StdbQuery<R> {
+        StdbQuery {
+            filter: None,
+            _row: PhantomData,
+        }
+    }
+}
+
+/// A typed `SELECT` query on the table of `R`, converted into the SQL string of a subscription.
+///
+/// Queries can be used wherever subscription queries are expected, e.g. [`StdbPlugin::with_subscription`](crate::StdbPlugin::with_subscription).
+/// A query comparing a column to a value without an SQL literal fails with a [`StdbQueryError`] instead of being subscribed to.
+pub struct StdbQuery<R: StdbTableRow> {
+    filter: Option<StdbFilter<R>>,
+    _row: PhantomData<fn() -> R>,
+}
+
+impl<R: StdbTableRow> StdbQuery<R> {
+    /// Only selects the rows matching the filter, built from the columns of the table.
+    ///
+    /// Calling it more than once selects the rows matching all the filters.
+    pub fn filter(mut self, filter: impl FnOnce(&R::Columns) -> StdbFilter<R>) -> Self {
+        let filter = filter(&R::COLUMNS);
+        self.filter = Some(match self.filter {
+            Some(previous) => previous.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Returns the SQL string of the query, or an error if one of its values has no SQL literal.
+    pub fn to_sql(&self) -> Result<String, StdbQueryError> {
+        match &self.filter {
+            Some(filter) => Ok(format!(
+                "SELECT * FROM {} WHERE {}",
+                R::TABLE_NAME,
+                filter.sql.clone()?
+            )),
+            None => Ok(format!("SELECT * FROM {}", R::TABLE_NAME)),
+        }
+    }
+}
+
+impl<R: StdbTableRow> Clone for StdbQuery<R> {
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            _row: PhantomData,
+        }
+    }
+}
+
+impl<R: StdbTableRow> std::fmt::Debug for StdbQuery<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_sql() {
+            Ok(sql) => f.debug_tuple("StdbQuery").field(&sql).finish(),
+            Err(err) => f.debug_tuple("StdbQuery").field(&err).finish(),
+        }
+    }
+}
+
+/// A query of a subscription, either an SQL string or a [`StdbQuery`].
+pub trait StdbSubscriptionQuery {
+    /// Returns the SQL string of the query.
+    fn into_sql(self) -> Result<String, StdbQueryError>;
+}
+
+impl<T: Into<String>> StdbSubscriptionQuery for T {
+    fn into_sql(self) -> Result<String, StdbQueryError> {
+        Ok(self.into())
+    }
+}
+
+impl<R: StdbTableRow> StdbSubscriptionQuery for StdbQuery<R> {
+    fn into_sql(self) -> Result<String, StdbQueryError> {
+        self.to_sql()
+    }
+}
+
+/// The error of a query comparing a column to a value that has no SQL literal, such as a NaN float.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StdbQueryError {
+    value: String,
+}
+
+impl std::fmt::Display for StdbQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot compare a column to {} in a query, it has no SQL literal",
+            self.value
+        )
+    }
+}
+
+impl std::error::Error for StdbQueryError {}
+
+/// A column of the table of `R`, holding values of type `T`.
+pub struct StdbColumn<R, T> {
+    name: &'static str,
+    _marker: PhantomData<fn(&R) -> &T>,
+}
+
+impl<R, T> StdbColumn<R, T> {
+    /// Creates the column named `name`, used by the code generated by [`stdb_bindings!`](crate::stdb_bindings).
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the name of the column.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<R, T: StdbSqlValue> StdbColumn<R, T> {
+    /// Selects the rows where the column is equal to the value.
+    pub fn eq(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare("=", value.into())
+    }
+
+    /// Selects the rows where the column is not equal to the value.
+    pub fn ne(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare("!=", value.into())
+    }
+
+    /// Selects the rows where the column is lower than the value.
+    pub fn lt(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare("<", value.into())
+    }
+
+    /// Selects the rows where the column is lower than or equal to the value.
+    pub fn le(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare("<=", value.into())
+    }
+
+    /// Selects the rows where the column is greater than the value.
+    pub fn gt(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare(">", value.into())
+    }
+
+    /// Selects the rows where the column is greater than or equal to the value.
+    pub fn ge(&self, value: impl Into<T>) -> StdbFilter<R> {
+        self.compare(">=", value.into())
+    }
+
+    fn compare(&self, operator: &str, value: T) -> StdbFilter<R> {
+        StdbFilter {
+            sql: value
+                .to_sql()
+                .map(|value| format!("{} {} {}", self.name, operator, value)),
+            _row: PhantomData,
+        }
+    }
+}
+
+impl<R, T> Clone for StdbColumn<R, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R, T> Copy for StdbColumn<R, T> {}
+
+impl<R, T> std::fmt::Debug for StdbColumn<R, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StdbColumn").field(&self.name).finish()
+    }
+}
+
+/// A condition on the rows of the table of `R`, built from its columns.
+///
+/// If one of its values has no SQL literal, the error is kept until the SQL string of the query is built.
+pub struct StdbFilter<R> {
+    sql: Result<String, StdbQueryError>,
+    _row: PhantomData<fn() -> R>,
+}
+
+impl<R> StdbFilter<R> {
+    /// Selects the rows matching both filters.
+    pub fn and(self, other: Self) -> Self {
+        Self {
+            sql: self.combine("AND", other),
+            _row: PhantomData,
+        }
+    }
+
+    /// Selects the rows matching either filter.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            sql: self.combine("OR", other),
+            _row: PhantomData,
+        }
+    }
+
+    fn combine(self, operator: &str, other: Self) -> Result<String, StdbQueryError> {
+        Ok(format!("({}) {} ({})", self.sql?, operator, other.sql?))
+    }
+}
+
+impl<R> Clone for StdbFilter<R> {
+    fn clone(&self) -> Self {
+        Self {
+            sql: self.sql.clone(),
+            _row: PhantomData,
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for StdbFilter<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.sql {
+            Ok(sql) => f.debug_tuple("StdbFilter").field(sql).finish(),
+            Err(err) => f.debug_tuple("StdbFilter").field(err).finish(),
+        }
+    }
+}
+
+/// A value that can be compared to a column in a query, written as an SQL literal.
+///
+/// Floats must be finite, as NaN and the infinities have no SQL literal: a query comparing a column to them fails.
+pub trait StdbSqlValue {
+    /// Returns the SQL literal of the value, or an error if it has none.
+    fn to_sql(&self) -> Result<String, StdbQueryError>;
+}
+
+macro_rules! impl_sql_value_display {
+    ($($ty:ty),*) => {
+        $(
+            impl StdbSqlValue for $ty {
+                fn to_sql(&self) -> Result<String, StdbQueryError> {
+                    Ok(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_value_display!(bool, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+macro_rules! impl_sql_value_float {
+    ($($ty:ty),*) => {
+        $(
+            impl StdbSqlValue for $ty {
+                fn to_sql(&self) -> Result<String, StdbQueryError> {
+                    if !self.is_finite() {
+                        return Err(StdbQueryError {
+                            value: self.to_string(),
+                        });
+                    }
+                    Ok(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_value_float!(f32, f64);
+
+impl StdbSqlValue for String {
+    fn to_sql(&self) -> Result<String, StdbQueryError> {
+        Ok(format!("'{}'", self.replace('\'', "''")))
+    }
+}
+
+impl StdbSqlValue for Identity {
+    fn to_sql(&self) -> Result<String, StdbQueryError> {
+        Ok(format!("0x{}", self.to_hex()))
+    }
+}
+
+impl StdbSqlValue for ConnectionId {
+    fn to_sql(&self) -> Result<String, StdbQueryError> {
+        Ok(format!("0x{}", self.to_hex()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Player;
+
+    struct PlayerColumns {
+        name: StdbColumn<Player, String>,
+        score: StdbColumn<Player, u32>,
+        speed: StdbColumn<Player, f32>,
+        online: StdbColumn<Player, bool>,
+    }
+
+    impl StdbTableRow for Player {
+        const TABLE_NAME: &'static str = "player";
+        type Columns = PlayerColumns;
+        const COLUMNS: PlayerColumns = PlayerColumns {
+            name: StdbColumn::new("name"),
+            score: StdbColumn::new("score"),
+            speed: StdbColumn::new("speed"),
+            online: StdbColumn::new("online"),
+        };
+    }
+
+    #[test]
+    fn selects_the_whole_table() {
+        assert_eq!(
+            Subscribe::table::<Player>().to_sql().unwrap(),
+            "SELECT * FROM player"
+        );
+    }
+
+    #[test]
+    fn filters_on_a_column() {
+        let query = Subscribe::table::<Player>().filter(|player| player.online.eq(true));
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT * FROM player WHERE online = true"
+        );
+    }
+
+    #[test]
+    fn combines_filters() {
+        let query = Subscribe::table::<Player>()
+            .filter(|player| player.score.lt(5u32).or(player.score.gt(10u32)))
+            .filter(|player| player.online.ne(false));
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT * FROM player WHERE ((score < 5) OR (score > 10)) AND (online != false)"
+        );
+
+        let filter = Player::COLUMNS
+            .score
+            .ge(1u32)
+            .and(Player::COLUMNS.speed.le(2.5));
+        assert_eq!(
+            format!("{filter:?}"),
+            "StdbFilter(\"(score >= 1) AND (speed <= 2.5)\")"
+        );
+    }
+
+    #[test]
+    fn escapes_string_quotes() {
+        let query = Subscribe::table::<Player>().filter(|player| player.name.eq("O'Brien"));
+        assert_eq!(
+            query.into_sql().unwrap(),
+            "SELECT * FROM player WHERE name = 'O''Brien'"
+        );
+    }
+
+    #[test]
+    fn rejects_nan() {
+        let query = Subscribe::table::<Player>()
+            .filter(|player| player.speed.eq(f32::NAN).or(player.online.eq(true)));
+        let err = query.to_sql().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot compare a column to NaN in a query, it has no SQL literal"
+        );
+        assert_eq!(query.into_sql(), Err(err));
+    }
+
+    #[test]
+    fn rejects_infinity() {
+        assert!(f64::INFINITY.to_sql().is_err());
+        assert!(f64::NEG_INFINITY.to_sql().is_err());
+        assert_eq!(f64::MAX.to_sql(), Ok(f64::MAX.to_string()));
+    }
+}
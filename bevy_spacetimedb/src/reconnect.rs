@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use spacetimedb_sdk::DbContext;
+
+use crate::{
+    StdbConnectEvent, StdbConnectedEvent, StdbConnectionErrorEvent, StdbConnectionState,
+    StdbDisconnectedEvent, StdbReconnectedEvent, StdbReconnectingEvent, connector::StdbConnector,
+};
+
+/// Passed into [`StdbPlugin::with_reconnect`](crate::StdbPlugin::with_reconnect) to determine
+/// how often and how many times the connection is re-established after it is lost.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// The delay before the first reconnection attempt.
+    pub initial_delay: Duration,
+    /// The upper bound of the delay between two reconnection attempts.
+    pub max_delay: Duration,
+    /// The factor by which the delay grows after each failed attempt.
+    pub multiplier: f32,
+    /// The number of attempts after which the plugin gives up, `None` to retry forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    /// Retry forever, always waiting `delay` between two attempts.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            initial_delay: delay,
+            max_delay: delay,
+            multiplier: 1.0,
+            max_attempts: None,
+        }
+    }
+
+    /// Retry forever, doubling the delay after each failed attempt, starting at `initial_delay` and up to `max_delay`.
+    pub fn exponential(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+
+    /// Sets the factor by which the delay grows after each failed attempt.
+    pub fn with_multiplier(mut self, multiplier: f32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the number of attempts after which the plugin gives up.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Returns the delay to wait before the given attempt, starting at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay.as_secs_f32() * self.multiplier.powi(exponent);
+
+        Duration::try_from_secs_f32(secs)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::exponential(Duration::from_secs(1), Duration::from_secs(30))
+    }
+}
+
+/// Tracks the progress of the reconnection.
+#[derive(Resource)]
+pub(crate) struct ReconnectState {
+    policy: ReconnectPolicy,
+    /// The number of the pending or last attempt, 0 when not reconnecting.
+    attempt: u32,
+    /// Counts down to the next attempt.
+    timer: Option<Timer>,
+    /// The token received with the last connection, used to reconnect as the same `Identity`.
+    access_token: Option<String>,
+}
+
+impl ReconnectState {
+    pub(crate) fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            attempt: 0,
+            timer: None,
+            access_token: None,
+        }
+    }
+
+    /// Schedules the next attempt, unless the policy allows no more attempts.
+    fn schedule_next(&mut self, writer: &mut EventWriter<StdbReconnectingEvent>) {
+        let attempt = self.attempt + 1;
+        if self.policy.max_attempts.is_some_and(|max| attempt > max) {
+            warn!(
+                "Giving up reconnecting to SpacetimeDB after {} attempts",
+                self.attempt
+            );
+            self.attempt = 0;
+            self.timer = None;
+            return;
+        }
+
+        let delay = self.policy.delay(attempt);
+        self.attempt = attempt;
+        self.timer = Some(Timer::new(delay, TimerMode::Once));
+        writer.write(StdbReconnectingEvent { attempt, delay });
+    }
+}
+
+/// Schedules a reconnection attempt when the connection is lost, or when an attempt failed.
+pub(crate) fn schedule_reconnect(
+    mut state: ResMut<ReconnectState>,
+    mut connected: EventReader<StdbConnectedEvent>,
+    mut disconnected: EventReader<StdbDisconnectedEvent>,
+    mut errors: EventReader<StdbConnectionErrorEvent>,
+    mut reconnecting: EventWriter<StdbReconnectingEvent>,
+    mut reconnected: EventWriter<StdbReconnectedEvent>,
+) {
+    for event in connected.read() {
+        state.access_token = Some(event.access_token.clone());
+        if state.attempt > 0 {
+            reconnected.write(StdbReconnectedEvent {
+                attempts: state.attempt,
+            });
+            state.attempt = 0;
+            state.timer = None;
+        }
+    }
+
+    // A disconnection without error was requested, it should not be undone.
+    let lost = disconnected.read().any(|event| event.err.is_some());
+    let failed = errors.read().count() > 0 && state.attempt > 0;
+
+    if (lost || failed) && state.timer.is_none() {
+        state.schedule_next(&mut reconnecting);
+    }
+}
+
+/// Opens a new connection once the delay of the pending attempt has elapsed.
+pub(crate) fn attempt_reconnect<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut state: ResMut<ReconnectState>,
+    connector: Res<StdbConnector<C, M>>,
+    mut connection_state: ResMut<StdbConnectionState>,
+) {
+    let Some(timer) = state.timer.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+    state.timer = None;
+
+    debug!("Reconnecting to SpacetimeDB, attempt {}", state.attempt);
+
+    let mut request: StdbConnectEvent = connector.last_request.lock().unwrap().clone();
+    if let Some(access_token) = &state.access_token {
+        request.token = Some(access_token.clone());
+    }
+
+    // On failure, a StdbConnectionErrorEvent schedules the next attempt.
+    match connector.connect_in_background(&request) {
+        Some(pending) => {
+            connection_state.set_if_neq(StdbConnectionState::Connecting);
+            commands.insert_resource(pending);
+        }
+        None => {
+            connection_state.set_if_neq(StdbConnectionState::Errored);
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{AddEventChannelAppExtensions, ReducerResultEvent, StdbPlugin};
+use crate::{
+    AddEventChannelAppExtensions, ReducerResultEvent, StdbPlugin, connector::ReducerBinder,
+};
 use bevy::app::App;
 use spacetimedb_sdk::__codegen as spacetime_codegen;
 use std::sync::mpsc::{Sender, channel};
@@ -23,14 +25,15 @@ impl<
     pub fn add_reducer<E: RegisterableReducerEvent<C, M> + Send + Sync + 'static>(
         mut self,
     ) -> Self {
-        // This callback manages the registration of the event.
-        let register_fn = move |app: &mut App, reducers: &C::Reducers| {
+        // This callback manages the registration of the event,
+        // and returns the callback binding it to a built connection.
+        let register_fn = move |app: &mut App| -> ReducerBinder<C> {
             let (send, recv) = channel::<ReducerResultEvent<E>>();
             app.add_event_channel(recv);
-            E::set_stdb_callback(reducers, send);
+            Box::new(move |reducers: &C::Reducers| E::set_stdb_callback(reducers, send.clone()))
         };
 
-        // The register_fn will get called once the connection is built.
+        // The register_fn will get called when the plugin is built.
         self.reducer_registers.push(Box::new(register_fn));
 
         self
@@ -0,0 +1,158 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::{
+        event::{EventCursor, Events},
+        schedule::IntoScheduleConfigs,
+    },
+    prelude::*,
+};
+use spacetimedb_sdk::__codegen as spacetime_codegen;
+
+use crate::{
+    DeleteEvent, InsertEvent, StdbPlugin, UpdateEvent,
+    channel_receiver::EventChannelSet,
+    entity_map::{RowEntityLookup, RowEntitySet},
+};
+
+/// How the [`InsertEvent`], [`UpdateEvent`] and [`DeleteEvent`] of the tables are delivered,
+/// set with [`StdbPlugin::with_row_event_delivery`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RowEventDelivery {
+    /// The events are buffered and read with an `EventReader`.
+    #[default]
+    Events,
+    /// The events are triggered for observers, and are not buffered.
+    ///
+    /// When the row has an entity in a [`StdbEntityMap`](crate::StdbEntityMap), the event targets it.
+    Triggers,
+    /// The events are both buffered and triggered.
+    EventsAndTriggers,
+}
+
+impl RowEventDelivery {
+    fn triggers(self) -> bool {
+        matches!(self, Self::Triggers | Self::EventsAndTriggers)
+    }
+}
+
+/// Marks the row events of the table `T` as triggered, as several tables can share the same row.
+#[derive(Resource)]
+struct RowTriggers<T>(PhantomData<fn() -> T>);
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Sets how the insert, update and delete events of the tables are delivered, see [`RowEventDelivery`].
+    ///
+    /// The triggered events are observed with `app.add_observer(|trigger: Trigger<InsertEvent<Player>>| ...)`,
+    /// or with an observer on the entity of the row when the table is mirrored.
+    /// The [`InsertUpdateEvent`](crate::InsertUpdateEvent) is always buffered.
+    pub fn with_row_event_delivery(mut self, delivery: RowEventDelivery) -> Self {
+        self.row_event_delivery = delivery;
+        self
+    }
+
+    /// Adds the systems triggering the row events of the table of `TRow`, if requested.
+    pub(crate) fn add_row_triggers<TRow: Send + Sync + Clone + 'static>(&self, app: &mut App) {
+        if !self.row_event_delivery.triggers()
+            || app.world().contains_resource::<RowTriggers<TRow>>()
+        {
+            return;
+        }
+        app.insert_resource(RowTriggers::<TRow>(PhantomData));
+
+        // Deleted rows are triggered while their entity is still alive, inserted and updated ones once it is spawned.
+        app.add_systems(
+            PreUpdate,
+            (
+                trigger_deleted_rows::<TRow>
+                    .after(EventChannelSet)
+                    .before(RowEntitySet),
+                trigger_inserted_rows::<TRow>.after(RowEntitySet),
+            ),
+        );
+
+        if self.row_event_delivery == RowEventDelivery::Triggers {
+            app.add_systems(
+                PreUpdate,
+                clear_row_events::<TRow>.after(trigger_inserted_rows::<TRow>),
+            );
+        }
+    }
+}
+
+/// Triggers the [`DeleteEvent`] of the rows deleted since the last run.
+fn trigger_deleted_rows<T: Send + Sync + Clone + 'static>(
+    world: &mut World,
+    mut deletes: Local<EventCursor<DeleteEvent<T>>>,
+) {
+    let Some(events) = world.get_resource::<Events<DeleteEvent<T>>>() else {
+        return;
+    };
+    let events: Vec<_> = deletes.read(events).cloned().collect();
+
+    let lookup = world
+        .get_resource::<RowEntityLookup<T>>()
+        .map(|lookup| lookup.0);
+    for event in events {
+        let entity = lookup.and_then(|lookup| lookup(world, &event.row));
+        trigger_row_event(world, event, entity);
+    }
+}
+
+/// Triggers the [`InsertEvent`] and [`UpdateEvent`] of the rows inserted and updated since the last run.
+fn trigger_inserted_rows<T: Send + Sync + Clone + 'static>(
+    world: &mut World,
+    mut inserts: Local<EventCursor<InsertEvent<T>>>,
+    mut updates: Local<EventCursor<UpdateEvent<T>>>,
+) {
+    let inserted: Vec<_> = world
+        .get_resource::<Events<InsertEvent<T>>>()
+        .map(|events| inserts.read(events).cloned().collect())
+        .unwrap_or_default();
+    let updated: Vec<_> = world
+        .get_resource::<Events<UpdateEvent<T>>>()
+        .map(|events| updates.read(events).cloned().collect())
+        .unwrap_or_default();
+
+    let lookup = world
+        .get_resource::<RowEntityLookup<T>>()
+        .map(|lookup| lookup.0);
+    for event in inserted {
+        let entity = lookup.and_then(|lookup| lookup(world, &event.row));
+        trigger_row_event(world, event, entity);
+    }
+    for event in updated {
+        let entity = lookup.and_then(|lookup| lookup(world, &event.new));
+        trigger_row_event(world, event, entity);
+    }
+}
+
+/// Triggers the event, targeting the entity of the row if it has one.
+fn trigger_row_event<E: Event>(world: &mut World, event: E, entity: Option<Entity>) {
+    match entity {
+        Some(entity) => world.trigger_targets(event, entity),
+        None => world.trigger(event),
+    }
+}
+
+/// Clears the buffered row events once they have been triggered.
+fn clear_row_events<T: Send + Sync + 'static>(
+    inserts: Option<ResMut<Events<InsertEvent<T>>>>,
+    updates: Option<ResMut<Events<UpdateEvent<T>>>>,
+    deletes: Option<ResMut<Events<DeleteEvent<T>>>>,
+) {
+    if let Some(mut inserts) = inserts {
+        inserts.clear();
+    }
+    if let Some(mut updates) = updates {
+        updates.clear();
+    }
+    if let Some(mut deletes) = deletes {
+        deletes.clear();
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    sync::{Arc, mpsc::Sender},
+    thread::{self, JoinHandle},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{BoxedFuture, IoTaskPool, Task, TaskPool, block_on},
+};
+use spacetimedb_sdk::DbContext;
+
+use crate::{StdbConnection, connector::StdbConnector, transactions::TransactionMessage};
+
+/// Determines how the connection processes the messages it receives,
+/// passed into [`StdbPlugin::with_run_mode`](crate::StdbPlugin::with_run_mode).
+pub enum StdbRunMode<C> {
+    /// Runs the connection on its own thread, the recommended function is `DbConnection::run_threaded`.
+    ///
+    /// Callbacks are processed as soon as the messages are received, and turned into events in `PreUpdate`.
+    Threaded(fn(&C) -> JoinHandle<()>),
+    /// Processes all the pending messages once per frame in `PreUpdate`, the recommended function is `DbConnection::frame_tick`.
+    ///
+    /// Callbacks are processed on the main schedule right before they are turned into events,
+    /// which makes their delivery deterministic and single-threaded.
+    FrameTick(fn(&C) -> spacetimedb_sdk::Result<()>),
+    /// Runs the connection as a task on Bevy's [`IoTaskPool`], the recommended future is `DbConnection::run_async`:
+    ///
+    /// ```ignore
+    /// StdbRunMode::Async(|conn: Arc<DbConnection>| Box::pin(async move { conn.run_async().await }))
+    /// ```
+    ///
+    /// Callbacks are processed as soon as the messages are received, and turned into events in `PreUpdate`.
+    Async(fn(Arc<C>) -> BoxedFuture<'static, spacetimedb_sdk::Result<()>>),
+    /// Runs the connection on its own thread, processing one message at a time,
+    /// the recommended function is `DbConnection::advance_one_message_blocking`.
+    ///
+    /// Callbacks are processed as soon as the messages are received, and turned into events in `PreUpdate`.
+    /// Unlike [`Self::Threaded`], the end of each message is known,
+    /// see [`StdbPlugin::with_transaction_events`](crate::StdbPlugin::with_transaction_events).
+    Messages(fn(&C) -> spacetimedb_sdk::Result<()>),
+    /// Runs the connection as a task on Bevy's [`IoTaskPool`], processing one message at a time,
+    /// the recommended future is `DbConnection::advance_one_message_async`:
+    ///
+    /// ```ignore
+    /// StdbRunMode::AsyncMessages(|conn: Arc<DbConnection>| {
+    ///     Box::pin(async move { conn.advance_one_message_async().await })
+    /// })
+    /// ```
+    ///
+    /// Callbacks are processed as soon as the messages are received, and turned into events in `PreUpdate`.
+    /// Unlike [`Self::Async`], the end of each message is known,
+    /// see [`StdbPlugin::with_transaction_events`](crate::StdbPlugin::with_transaction_events).
+    AsyncMessages(fn(Arc<C>) -> BoxedFuture<'static, spacetimedb_sdk::Result<()>>),
+}
+
+impl<C> Clone for StdbRunMode<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for StdbRunMode<C> {}
+
+impl<C> StdbRunMode<C> {
+    /// Returns `true` if the end of each message is committed to the transaction channel.
+    pub(crate) fn commits(&self) -> bool {
+        matches!(
+            self,
+            Self::FrameTick(_) | Self::Messages(_) | Self::AsyncMessages(_)
+        )
+    }
+}
+
+impl<C: Send + Sync + 'static> StdbRunMode<C> {
+    /// Starts running the connection, returns `None` if it is driven by the [`frame_tick`] system instead.
+    ///
+    /// The end of each message is committed to `transactions`, if any, when it is known.
+    pub(crate) fn start(
+        &self,
+        conn: &Arc<C>,
+        transactions: Option<Sender<TransactionMessage>>,
+    ) -> Option<RunHandle> {
+        match self {
+            Self::Threaded(run_fn) => Some(RunHandle::Thread(run_fn(conn))),
+            Self::FrameTick(_) => None,
+            Self::Async(run_fn) => {
+                let task_pool = IoTaskPool::get_or_init(TaskPool::new);
+                Some(RunHandle::Task(task_pool.spawn(run_fn(conn.clone()))))
+            }
+            Self::Messages(advance) => {
+                let conn = conn.clone();
+                let advance = *advance;
+                Some(RunHandle::MessageThread(thread::spawn(move || {
+                    let commit = || {
+                        if let Some(transactions) = &transactions {
+                            let _ = transactions.send(TransactionMessage::Commit);
+                        }
+                    };
+                    // Stops once the connection is closed, delivering what was received before.
+                    while advance(&conn).is_ok() {
+                        commit();
+                    }
+                    commit();
+                })))
+            }
+            Self::AsyncMessages(advance) => {
+                let conn = conn.clone();
+                let advance = *advance;
+                let task_pool = IoTaskPool::get_or_init(TaskPool::new);
+                Some(RunHandle::Task(task_pool.spawn(async move {
+                    let commit = || {
+                        if let Some(transactions) = &transactions {
+                            let _ = transactions.send(TransactionMessage::Commit);
+                        }
+                    };
+                    // Stops once the connection is closed, delivering what was received before.
+                    while advance(conn.clone()).await.is_ok() {
+                        commit();
+                    }
+                    commit();
+                    Ok(())
+                })))
+            }
+        }
+    }
+}
+
+/// The thread or task running a connection.
+pub(crate) enum RunHandle {
+    /// Started by [`StdbRunMode::Threaded`].
+    Thread(JoinHandle<()>),
+    /// Started by [`StdbRunMode::Async`] or [`StdbRunMode::AsyncMessages`], it holds its own reference to the connection.
+    Task(Task<spacetimedb_sdk::Result<()>>),
+    /// Started by [`StdbRunMode::Messages`], it holds its own reference to the connection.
+    MessageThread(JoinHandle<()>),
+}
+
+impl RunHandle {
+    /// Returns `true` once the connection stopped running.
+    pub(crate) fn is_finished(&self) -> bool {
+        match self {
+            Self::Thread(handle) | Self::MessageThread(handle) => handle.is_finished(),
+            Self::Task(task) => task.is_finished(),
+        }
+    }
+
+    /// Waits for the connection to stop running.
+    pub(crate) fn join(self) {
+        match self {
+            Self::Thread(handle) | Self::MessageThread(handle) => {
+                let _ = handle.join();
+            }
+            Self::Task(task) => {
+                if let Err(err) = block_on(task) {
+                    warn!("The SpacetimeDB connection task failed: {}", err);
+                }
+            }
+        }
+    }
+}
+
+/// Processes the pending messages of the connection when running in [`StdbRunMode::FrameTick`].
+pub(crate) fn frame_tick<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+>(
+    conn: Option<Res<StdbConnection<C>>>,
+    connector: Res<StdbConnector<C, M>>,
+) {
+    let StdbRunMode::FrameTick(tick) = connector.run_mode else {
+        return;
+    };
+    // Ticked until the disconnection is delivered, as `disconnect` only requests it.
+    let Some(conn) = conn.filter(|conn| !conn.is_closed()) else {
+        return;
+    };
+
+    // A lost connection is reported through the on_disconnect callback.
+    if let Err(err) = tick(conn.conn()) {
+        debug!("Failed to process SpacetimeDB messages: {}", err);
+    }
+
+    // All the pending messages were processed, their transactions are complete.
+    if let Some(transactions) = &connector.transaction_sender {
+        let _ = transactions.send(TransactionMessage::Commit);
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use spacetimedb_sdk::DbContext;
+
+use crate::{StdbConnection, StdbDisconnectedEvent, connector::StdbConnector};
+
+/// How often the run thread or task is polled while waiting for it to finish.
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Closes the connection when the app exits, and waits for the run thread or task to finish.
+pub(crate) fn shutdown_on_exit<
+    C: spacetimedb_sdk::__codegen::DbConnection<Module = M> + DbContext + Send + Sync + 'static,
+    M: spacetimedb_sdk::__codegen::SpacetimeModule<DbConnection = C>,
+>(
+    mut exits: EventReader<AppExit>,
+    conn: Option<ResMut<StdbConnection<C>>>,
+    connector: Res<StdbConnector<C, M>>,
+    mut disconnected: EventWriter<StdbDisconnectedEvent>,
+) {
+    if exits.read().last().is_none() {
+        return;
+    }
+    let Some(mut conn) = conn else {
+        return;
+    };
+
+    // A connection that was already closed had its disconnection reported, it must not be reported twice.
+    let was_active = conn.is_active();
+    if was_active && let Err(err) = conn.disconnect() {
+        warn!("Failed to disconnect from SpacetimeDB: {}", err);
+    }
+
+    if let Some(run_handle) = conn.take_run_handle() {
+        let deadline = Instant::now() + connector.shutdown_timeout;
+        while !run_handle.is_finished() && Instant::now() < deadline {
+            thread::sleep(JOIN_POLL_INTERVAL);
+        }
+
+        if run_handle.is_finished() {
+            run_handle.join();
+        } else {
+            warn!(
+                "The SpacetimeDB connection did not stop running within {:?}",
+                connector.shutdown_timeout
+            );
+        }
+    }
+
+    // The app stops before the SDK callback could be turned into an event, send it right away instead.
+    if was_active {
+        disconnected.write(StdbDisconnectedEvent { err: None });
+    }
+}
@@ -1,18 +1,78 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
 use bevy::prelude::Resource;
 use spacetimedb_sdk::{ConnectionId, DbContext, Identity, Result};
 
+use crate::run_mode::RunHandle;
+
 #[derive(Resource)]
 /// A connection to the SpacetimeDB server, as a Bevy resource.
 /// This struct is a wrapper around a concrete-typed `DbContext` in your auto-generated.
+///
+/// The connection is owned by the resource: it is closed and dropped when the resource is removed or replaced.
 pub struct StdbConnection<T: DbContext + 'static> {
     /// The underlying connection.
-    conn: &'static T,
+    conn: Arc<T>,
+    /// The thread or task running the connection, if any.
+    run_handle: Option<RunHandle>,
+    /// Set by the SDK callbacks once the disconnection or the connection error was delivered.
+    closed: Arc<AtomicBool>,
 }
 
 impl<T: DbContext> StdbConnection<T> {
     /// Create a new connection to the SpacetimeDB server.
-    pub fn new(conn: &'static T) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<T>) -> Self {
+        Self {
+            conn,
+            run_handle: None,
+            closed: Arc::default(),
+        }
+    }
+
+    /// Sets the thread or task running the connection, so it can be joined on shutdown.
+    pub(crate) fn with_run_handle(mut self, run_handle: Option<RunHandle>) -> Self {
+        self.run_handle = run_handle;
+        self
+    }
+
+    /// Sets the flag the SDK callbacks set once the connection has nothing more to deliver.
+    pub(crate) fn with_closed(mut self, closed: Arc<AtomicBool>) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    /// Returns `true` once the disconnection or the connection error was delivered by the SDK callbacks.
+    ///
+    /// Unlike `is_active`, it is only set after the messages received before the disconnection are processed.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Takes the thread or task running the connection, if any.
+    pub(crate) fn take_run_handle(&mut self) -> Option<RunHandle> {
+        self.run_handle.take()
+    }
+}
+
+impl<T: DbContext + 'static> Drop for StdbConnection<T> {
+    fn drop(&mut self) {
+        // The run task or thread holds its own reference to the connection, let it finish processing the disconnection.
+        let run_task_refs = match self.run_handle.take() {
+            Some(RunHandle::Task(task)) => {
+                task.detach();
+                1
+            }
+            Some(RunHandle::MessageThread(_)) => 1,
+            _ => 0,
+        };
+
+        // Nothing else can use the connection anymore, close it so it does not outlive the resource.
+        if Arc::strong_count(&self.conn) == 1 + run_task_refs && self.conn.is_active() {
+            let _ = self.conn.disconnect();
+        }
     }
 }
 
@@ -65,6 +125,6 @@ impl<T: DbContext> StdbConnection<T> {
 
     /// Access to the underlying connection, it's not recommended to use this method directly.
     pub fn conn(&self) -> &T {
-        self.conn
+        &self.conn
     }
 }
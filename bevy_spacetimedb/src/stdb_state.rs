@@ -0,0 +1,58 @@
+use bevy::{
+    prelude::*,
+    state::{app::StatesPlugin, state::FreelyMutableState},
+};
+
+use crate::{StdbConnectionState, connection_state::update_connection_state};
+
+/// The connection lifecycle as Bevy [`States`], enabled with [`StdbPlugin::with_state`](crate::StdbPlugin::with_state).
+///
+/// Your own states can be driven instead by implementing `From<StdbState>` for them.
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StdbState {
+    /// No connection is open, either because none was requested yet or because it was closed.
+    #[default]
+    Disconnected,
+    /// A connection is being opened.
+    Connecting,
+    /// The connection is established.
+    Connected,
+    /// The connection could not be opened, or was lost because of an error.
+    Errored,
+}
+
+impl From<&StdbConnectionState> for StdbState {
+    fn from(state: &StdbConnectionState) -> Self {
+        match state {
+            StdbConnectionState::Disconnected => Self::Disconnected,
+            StdbConnectionState::Connecting => Self::Connecting,
+            StdbConnectionState::Connected { .. } => Self::Connected,
+            StdbConnectionState::Errored => Self::Errored,
+        }
+    }
+}
+
+/// Initializes the state `S` and the system driving it.
+pub(crate) fn register_state<S: FreelyMutableState + FromWorld + From<StdbState>>(app: &mut App) {
+    if !app.is_plugin_added::<StatesPlugin>() {
+        app.add_plugins(StatesPlugin);
+    }
+    if !app.world().contains_resource::<State<S>>() {
+        app.init_state::<S>();
+    }
+
+    app.add_systems(
+        PreUpdate,
+        drive_state::<S>
+            .after(update_connection_state)
+            .run_if(resource_changed::<StdbConnectionState>),
+    );
+}
+
+/// Sets the next state `S` whenever the [`StdbConnectionState`] changes.
+fn drive_state<S: FreelyMutableState + From<StdbState>>(
+    connection_state: Res<StdbConnectionState>,
+    mut next_state: ResMut<NextState<S>>,
+) {
+    next_state.set(S::from(StdbState::from(&*connection_state)));
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::{
+    StdbSubscriptionId, StdbSubscriptionQuery, StdbSubscriptionState, StdbSubscriptions,
+    subscriptions::SubscriptionQueries,
+};
+
+/// A subscription tied to the lifetime of an entity.
+///
+/// Inserting the component subscribes to its queries through the [`StdbSubscriptions`] resource,
+/// removing it or despawning the entity unsubscribes from them.
+/// The component tracks the state of the subscription, so it can be used along with `StateScoped`
+/// to keep rows in the client cache only while in a given state.
+#[derive(Component, Debug, Clone)]
+pub struct StdbSubscription {
+    queries: SubscriptionQueries,
+    id: Option<StdbSubscriptionId>,
+    state: StdbSubscriptionState,
+}
+
+impl StdbSubscription {
+    /// Creates a subscription to the queries, applied once the component is inserted.
+    pub fn new<Q: StdbSubscriptionQuery>(queries: impl IntoIterator<Item = Q>) -> Self {
+        Self {
+            queries: SubscriptionQueries::new(queries),
+            id: None,
+            state: StdbSubscriptionState::Pending,
+        }
+    }
+
+    /// Returns the queries of the subscription.
+    pub fn queries(&self) -> &[String] {
+        &self.queries.queries
+    }
+
+    /// Returns the id of the subscription in the [`StdbSubscriptions`] resource, once the component is inserted.
+    pub fn id(&self) -> Option<StdbSubscriptionId> {
+        self.id
+    }
+
+    /// Returns the state of the subscription.
+    pub fn state(&self) -> &StdbSubscriptionState {
+        &self.state
+    }
+
+    /// Returns `true` if the rows of the subscription are in the client cache.
+    pub fn is_applied(&self) -> bool {
+        self.state == StdbSubscriptionState::Applied
+    }
+}
+
+/// Subscribes to the queries of an inserted [`StdbSubscription`].
+pub(crate) fn subscribe_entity(
+    trigger: Trigger<OnInsert, StdbSubscription>,
+    mut query: Query<&mut StdbSubscription>,
+    mut subscriptions: ResMut<StdbSubscriptions>,
+) {
+    let Ok(mut subscription) = query.get_mut(trigger.target()) else {
+        return;
+    };
+    let id = subscriptions.add(subscription.queries.clone());
+    subscription.id = Some(id);
+    subscription.state = subscriptions.state(id).unwrap().clone();
+}
+
+/// Unsubscribes from the queries of a [`StdbSubscription`] that is replaced, removed or despawned.
+pub(crate) fn unsubscribe_entity(
+    trigger: Trigger<OnReplace, StdbSubscription>,
+    query: Query<&StdbSubscription>,
+    mut subscriptions: ResMut<StdbSubscriptions>,
+) {
+    if let Ok(subscription) = query.get(trigger.target())
+        && let Some(id) = subscription.id
+    {
+        subscriptions.unsubscribe(id);
+    }
+}
+
+/// Copies the state of the subscriptions into their [`StdbSubscription`] components.
+pub(crate) fn sync_subscription_entities(
+    mut query: Query<&mut StdbSubscription>,
+    subscriptions: Res<StdbSubscriptions>,
+) {
+    for mut subscription in query.iter_mut() {
+        // Only assign on change, so `Changed<StdbSubscription>` can be used to react to the state.
+        if let Some(id) = subscription.id
+            && let Some(state) = subscriptions.state(id)
+            && subscription.state != *state
+        {
+            subscription.state = state.clone();
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{marker::PhantomData, sync::Mutex};
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::schedule::IntoScheduleConfigs,
+    platform::collections::HashMap,
+    prelude::*,
+};
+use spacetimedb_sdk::{
+    __codegen::{self as spacetime_codegen, SubscriptionHandle},
+    DbContext,
+};
+
+use crate::{
+    StdbConnectedEvent, StdbConnection, StdbConnectionState, StdbDisconnectedEvent, StdbPlugin,
+    StdbQueryError, StdbSubscriptionQuery, SubscriptionAppliedEvent, SubscriptionErrorEvent,
+    connection_state::update_connection_state,
+    subscription_entity::{subscribe_entity, sync_subscription_entities, unsubscribe_entity},
+    transactions::RowEventSender,
+};
+
+/// Identifies a subscription managed by [`StdbSubscriptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StdbSubscriptionId(u32);
+
+/// The state of a subscription managed by [`StdbSubscriptions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StdbSubscriptionState {
+    /// The subscription is waiting for the connection to be established.
+    Pending,
+    /// The subscription was sent, and is waiting to be applied.
+    Applying,
+    /// The rows of the subscription are in the client cache.
+    Applied,
+    /// The subscription failed with the given error.
+    Failed(String),
+}
+
+/// The SQL strings of the queries of a subscription, along with the error of the first query that could not be built.
+#[derive(Debug, Clone)]
+pub(crate) struct SubscriptionQueries {
+    pub(crate) queries: Vec<String>,
+    pub(crate) error: Option<StdbQueryError>,
+}
+
+impl SubscriptionQueries {
+    pub(crate) fn new<Q: StdbSubscriptionQuery>(queries: impl IntoIterator<Item = Q>) -> Self {
+        let mut error = None;
+        let queries = queries
+            .into_iter()
+            .filter_map(|query| {
+                query
+                    .into_sql()
+                    .map_err(|err| error.get_or_insert(err))
+                    .ok()
+            })
+            .collect();
+        Self { queries, error }
+    }
+}
+
+/// A subscription managed by [`StdbSubscriptions`].
+#[derive(Debug)]
+struct ManagedSubscription {
+    id: StdbSubscriptionId,
+    queries: Vec<String>,
+    /// The tables the queries select from, see [`table_key`].
+    tables: Vec<String>,
+    state: StdbSubscriptionState,
+    /// Set if one of the queries could not be built, the subscription is then never applied.
+    error: Option<StdbQueryError>,
+}
+
+/// The subscriptions applied by the plugin on each connection, as a Bevy resource.
+///
+/// The subscriptions set with [`StdbPlugin::with_subscription`] are added to it when the plugin is built,
+/// more can be added or removed at runtime. They are applied once connected, and applied again after a reconnection.
+/// The progress is reported with the [`SubscriptionAppliedEvent`] and [`SubscriptionErrorEvent`] events.
+#[derive(Resource, Debug, Default)]
+pub struct StdbSubscriptions {
+    subscriptions: Vec<ManagedSubscription>,
+    /// The subscriptions removed since the last run of [`apply_subscriptions`], to unsubscribe from.
+    removed: Vec<StdbSubscriptionId>,
+    next_id: u32,
+}
+
+impl StdbSubscriptions {
+    /// Adds a subscription to the queries, it is applied as soon as the connection is established.
+    ///
+    /// If one of the queries is a [`StdbQuery`](crate::StdbQuery) that could not be built,
+    /// the subscription is never applied and its state is [`StdbSubscriptionState::Failed`].
+    pub fn subscribe<Q: StdbSubscriptionQuery>(
+        &mut self,
+        queries: impl IntoIterator<Item = Q>,
+    ) -> StdbSubscriptionId {
+        self.add(SubscriptionQueries::new(queries))
+    }
+
+    pub(crate) fn add(&mut self, queries: SubscriptionQueries) -> StdbSubscriptionId {
+        let id = StdbSubscriptionId(self.next_id);
+        self.next_id += 1;
+        let state = match &queries.error {
+            Some(err) => {
+                error!("Invalid SpacetimeDB subscription query: {}", err);
+                StdbSubscriptionState::Failed(err.to_string())
+            }
+            None => StdbSubscriptionState::Pending,
+        };
+        self.subscriptions.push(ManagedSubscription {
+            id,
+            tables: queries
+                .queries
+                .iter()
+                .flat_map(|query| query_tables(query))
+                .collect(),
+            queries: queries.queries,
+            state,
+            error: queries.error,
+        });
+        id
+    }
+
+    /// Removes a subscription, unsubscribing from it if it was sent, as soon as it is applied.
+    ///
+    /// Returns `false` if there is no such subscription.
+    pub fn unsubscribe(&mut self, id: StdbSubscriptionId) -> bool {
+        let Some(index) = self.subscriptions.iter().position(|sub| sub.id == id) else {
+            return false;
+        };
+        self.subscriptions.remove(index);
+        self.removed.push(id);
+        true
+    }
+
+    /// Returns the state of a subscription, if it exists.
+    pub fn state(&self, id: StdbSubscriptionId) -> Option<&StdbSubscriptionState> {
+        self.get(id).map(|sub| &sub.state)
+    }
+
+    /// Returns the queries of a subscription, if it exists.
+    pub fn queries(&self, id: StdbSubscriptionId) -> Option<&[String]> {
+        self.get(id).map(|sub| sub.queries.as_slice())
+    }
+
+    /// Returns `true` if all the subscriptions are applied.
+    pub fn all_applied(&self) -> bool {
+        self.subscriptions
+            .iter()
+            .all(|sub| sub.state == StdbSubscriptionState::Applied)
+    }
+
+    /// Iterates over the subscriptions with their queries and state.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (StdbSubscriptionId, &[String], &StdbSubscriptionState)> {
+        self.subscriptions
+            .iter()
+            .map(|sub| (sub.id, sub.queries.as_slice(), &sub.state))
+    }
+
+    /// Returns `true` once the subscriptions selecting from the table are all applied,
+    /// a table none of them selects from is never applied.
+    pub(crate) fn is_table_applied(&self, table: &str) -> bool {
+        let table = table_key(table);
+        let mut covering = self
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.tables.contains(&table))
+            .peekable();
+        covering.peek().is_some() && covering.all(|sub| sub.state == StdbSubscriptionState::Applied)
+    }
+
+    fn get(&self, id: StdbSubscriptionId) -> Option<&ManagedSubscription> {
+        self.subscriptions.iter().find(|sub| sub.id == id)
+    }
+
+    fn get_mut(&mut self, id: StdbSubscriptionId) -> Option<&mut ManagedSubscription> {
+        self.subscriptions.iter_mut().find(|sub| sub.id == id)
+    }
+}
+
+/// The systems updating and applying the subscriptions.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SubscriptionSet;
+
+/// The handles of the subscriptions applied on the current connection.
+#[derive(Resource)]
+struct SubscriptionHandles<M: spacetime_codegen::SpacetimeModule> {
+    handles: Mutex<HashMap<StdbSubscriptionId, M::SubscriptionHandle>>,
+    /// The handles of the removed subscriptions that were not applied yet, unsubscribed from once they are.
+    removed: Mutex<Vec<M::SubscriptionHandle>>,
+    /// Sent through the transaction channel if transaction events are enabled, to be delivered after the initial rows.
+    send_applied: RowEventSender<SubscriptionAppliedEvent>,
+    send_error: RowEventSender<SubscriptionErrorEvent>,
+    _module: PhantomData<fn() -> M>,
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Subscribes to the queries once connected, and again after each reconnection.
+    ///
+    /// Each call adds one subscription to the [`StdbSubscriptions`] resource,
+    /// which can also be used to add or remove subscriptions at runtime.
+    /// Subscriptions tied to an entity can be added with the [`StdbSubscription`](crate::StdbSubscription) component.
+    pub fn with_subscription<Q: StdbSubscriptionQuery>(
+        mut self,
+        queries: impl IntoIterator<Item = Q>,
+    ) -> Self {
+        self.subscriptions.push(SubscriptionQueries::new(queries));
+        self
+    }
+}
+
+/// Inserts the [`StdbSubscriptions`] resource with the initial subscriptions, and the systems managing them.
+pub(crate) fn register_subscriptions<
+    C: spacetime_codegen::DbConnection<Module = M>
+        + DbContext<SubscriptionBuilder = spacetime_codegen::SubscriptionBuilder<M>>,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+>(
+    plugin: &StdbPlugin<C, M>,
+    app: &mut App,
+) {
+    let mut subscriptions = StdbSubscriptions::default();
+    for queries in plugin.subscriptions.iter() {
+        subscriptions.add(queries.clone());
+    }
+
+    let send_applied = plugin.row_event_sender::<SubscriptionAppliedEvent>(app);
+    let send_error = plugin.row_event_sender::<SubscriptionErrorEvent>(app);
+    app.insert_resource(subscriptions)
+        .insert_resource(SubscriptionHandles::<M> {
+            handles: Mutex::default(),
+            removed: Mutex::default(),
+            send_applied,
+            send_error,
+            _module: PhantomData,
+        })
+        .add_systems(
+            PreUpdate,
+            (
+                update_subscription_states::<M>,
+                apply_subscriptions::<C, M>,
+                sync_subscription_entities,
+            )
+                .chain()
+                .in_set(SubscriptionSet)
+                .after(update_connection_state),
+        )
+        .add_observer(subscribe_entity)
+        .add_observer(unsubscribe_entity);
+}
+
+/// Updates the state of the subscriptions from the connection and subscription events.
+fn update_subscription_states<M: spacetime_codegen::SpacetimeModule>(
+    mut subscriptions: ResMut<StdbSubscriptions>,
+    handles: Res<SubscriptionHandles<M>>,
+    mut connected: EventReader<StdbConnectedEvent>,
+    mut disconnected: EventReader<StdbDisconnectedEvent>,
+    mut applied: EventReader<SubscriptionAppliedEvent>,
+    mut errors: EventReader<SubscriptionErrorEvent>,
+) {
+    // A new connection starts without any subscription, they are all applied again.
+    let reconnected = connected.read().last().is_some();
+    let lost = disconnected.read().last().is_some();
+    if reconnected || lost {
+        handles.handles.lock().unwrap().clear();
+        handles.removed.lock().unwrap().clear();
+        for sub in subscriptions
+            .subscriptions
+            .iter_mut()
+            .filter(|sub| sub.error.is_none())
+        {
+            sub.state = StdbSubscriptionState::Pending;
+        }
+    }
+
+    // Events from a previous connection are ignored, as the subscriptions are pending again.
+    for event in applied.read() {
+        if let Some(sub) = subscriptions.get_mut(event.id)
+            && sub.state == StdbSubscriptionState::Applying
+        {
+            sub.state = StdbSubscriptionState::Applied;
+        }
+    }
+    for event in errors.read() {
+        if let Some(sub) = subscriptions.get_mut(event.id)
+            && sub.state == StdbSubscriptionState::Applying
+        {
+            sub.state = StdbSubscriptionState::Failed(event.err.to_string());
+        }
+    }
+}
+
+/// Applies the pending subscriptions once connected, and unsubscribes from the removed ones.
+fn apply_subscriptions<
+    C: spacetime_codegen::DbConnection<Module = M>
+        + DbContext<SubscriptionBuilder = spacetime_codegen::SubscriptionBuilder<M>>,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+>(
+    conn: Option<Res<StdbConnection<C>>>,
+    state: Res<StdbConnectionState>,
+    mut subscriptions: ResMut<StdbSubscriptions>,
+    handles: Res<SubscriptionHandles<M>>,
+) {
+    let mut handles_map = handles.handles.lock().unwrap();
+    let mut removed_handles = handles.removed.lock().unwrap();
+
+    // A subscription can only be unsubscribed from once applied, until then its handle is kept.
+    let removed = std::mem::take(&mut subscriptions.removed);
+    removed_handles.extend(removed.iter().filter_map(|id| handles_map.remove(id)));
+    removed_handles.retain(|handle| {
+        if handle.is_ended() {
+            return false;
+        }
+        if !handle.is_active() {
+            return true;
+        }
+        if let Err(err) = handle.clone().unsubscribe() {
+            warn!("Failed to unsubscribe from SpacetimeDB: {}", err);
+        }
+        false
+    });
+
+    let Some(conn) = conn.filter(|conn| state.is_connected() && conn.is_active()) else {
+        return;
+    };
+    for sub in subscriptions
+        .subscriptions
+        .iter_mut()
+        .filter(|sub| sub.state == StdbSubscriptionState::Pending)
+    {
+        let id = sub.id;
+        let send_applied = handles.send_applied.clone();
+        let send_error = handles.send_error.clone();
+        let handle = conn
+            .subscription_builder()
+            .on_applied(move |_ctx| {
+                send_applied.send(SubscriptionAppliedEvent { id }, None);
+            })
+            .on_error(move |_ctx, err| {
+                send_error.send(SubscriptionErrorEvent { id, err }, None);
+            })
+            .subscribe(sub.queries.as_slice());
+        handles_map.insert(id, handle);
+        sub.state = StdbSubscriptionState::Applying;
+    }
+}
+
+/// Returns the tables a query selects from, as given by [`table_key`].
+fn query_tables(query: &str) -> Vec<String> {
+    let mut words = query
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '"'))
+        .filter(|word| !word.is_empty());
+
+    let mut tables = Vec::new();
+    while let Some(word) = words.next() {
+        if (word.eq_ignore_ascii_case("from") || word.eq_ignore_ascii_case("join"))
+            && let Some(table) = words.next()
+        {
+            tables.push(table_key(table));
+        }
+    }
+    tables
+}
+
+/// Returns the name of a table without its quotes and ignoring its case, as it is compared to the queries.
+fn table_key(name: &str) -> String {
+    name.trim_matches('"').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_tables_of_a_query() {
+        assert_eq!(
+            query_tables("SELECT * FROM player WHERE online = true"),
+            ["player"]
+        );
+        assert_eq!(query_tables("select * from Player"), ["player"]);
+        assert_eq!(
+            query_tables(r#"SELECT p.* FROM player p JOIN "Guild" g ON p.guild_id = g.id"#),
+            ["player", "guild"]
+        );
+        assert!(query_tables("not a query").is_empty());
+    }
+
+    #[test]
+    fn compares_table_names_without_quotes_and_case() {
+        assert_eq!(table_key(r#""Points""#), "points");
+        assert_eq!(table_key("game_server"), "game_server");
+    }
+
+    #[test]
+    fn tables_are_applied_once_all_their_subscriptions_are() {
+        let mut subscriptions = StdbSubscriptions::default();
+        let players = subscriptions.subscribe(["SELECT * FROM player"]);
+        let online = subscriptions.subscribe(["SELECT * FROM Player WHERE online = true"]);
+        assert!(!subscriptions.is_table_applied("player"));
+
+        subscriptions.get_mut(players).unwrap().state = StdbSubscriptionState::Applied;
+        assert!(!subscriptions.is_table_applied("player"));
+
+        subscriptions.get_mut(online).unwrap().state = StdbSubscriptionState::Applied;
+        assert!(subscriptions.is_table_applied("player"));
+        assert!(subscriptions.is_table_applied(r#""Player""#));
+
+        // A table none of the subscriptions selects from is never applied.
+        assert!(!subscriptions.is_table_applied("guild"));
+    }
+}
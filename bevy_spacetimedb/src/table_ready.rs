@@ -0,0 +1,111 @@
+use std::{any::type_name, marker::PhantomData};
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::schedule::IntoScheduleConfigs,
+    prelude::*,
+};
+use spacetimedb_sdk::__codegen as spacetime_codegen;
+
+use crate::{StdbPlugin, StdbSubscriptions, StdbTableRow, subscriptions::SubscriptionSet};
+
+/// An event sent when the rows of the table of `T` are in the client cache,
+/// once the subscriptions selecting from it are applied.
+///
+/// It is sent again when the subscriptions are applied after a reconnection.
+/// The rows received until then are sent as [`InsertEvent`](crate::InsertEvent) with `initial` set.
+#[derive(Event)]
+pub struct StdbTableSynced<T> {
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for StdbTableSynced<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdbTableSynced")
+            .field("row", &type_name::<T>())
+            .finish()
+    }
+}
+
+/// Whether the rows of the table of `T` are in the client cache, as a Bevy resource.
+///
+/// It is added by [`StdbPlugin::with_table_ready`]. The table is ready once the subscriptions of [`StdbSubscriptions`]
+/// selecting from it are applied, it is never ready if none of them does. It is no longer ready when the connection is lost,
+/// see [`stdb_table_ready`] to use it as a run condition.
+#[derive(Resource)]
+pub struct StdbTableReady<T> {
+    ready: bool,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T> StdbTableReady<T> {
+    /// Returns `true` if the rows of the table are in the client cache.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+}
+
+impl<T> std::fmt::Debug for StdbTableReady<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdbTableReady")
+            .field("row", &type_name::<T>())
+            .field("ready", &self.ready)
+            .finish()
+    }
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Tracks whether the rows of the table of `T` are in the client cache,
+    /// with the [`StdbTableReady`] resource and the [`StdbTableSynced`] event.
+    ///
+    /// The table is found in the subscription queries by [`StdbTableRow::TABLE_NAME`].
+    /// It is called for every table by [`StdbPlugin::add_all_tables`] when the bindings are registered with
+    /// [`stdb_bindings!`](crate::stdb_bindings).
+    pub fn with_table_ready<T: StdbTableRow + Send + Sync>(mut self) -> Self {
+        self.table_ready_registers.push(register_table_ready::<T>);
+        self
+    }
+}
+
+/// Adds the [`StdbTableReady`] resource of the table, and the system sending its [`StdbTableSynced`] event.
+fn register_table_ready<T: StdbTableRow + Send + Sync>(app: &mut App) {
+    if app.world().contains_resource::<StdbTableReady<T>>() {
+        return;
+    }
+
+    app.add_event::<StdbTableSynced<T>>()
+        .insert_resource(StdbTableReady::<T> {
+            ready: false,
+            _row: PhantomData,
+        })
+        .add_systems(PreUpdate, update_table_ready::<T>.after(SubscriptionSet));
+}
+
+/// Updates the [`StdbTableReady`] of the table from the state of the subscriptions.
+fn update_table_ready<T: StdbTableRow + Send + Sync>(
+    mut table_ready: ResMut<StdbTableReady<T>>,
+    subscriptions: Res<StdbSubscriptions>,
+    mut synced: EventWriter<StdbTableSynced<T>>,
+) {
+    let ready = subscriptions.is_table_applied(T::TABLE_NAME);
+    if table_ready.ready == ready {
+        return;
+    }
+
+    table_ready.ready = ready;
+    if ready {
+        synced.write(StdbTableSynced { _row: PhantomData });
+    }
+}
+
+/// A run condition that is `true` while the rows of the table of `T` are in the client cache, see [`StdbTableReady`].
+pub fn stdb_table_ready<T: Send + Sync + 'static>()
+-> impl FnMut(Option<Res<StdbTableReady<T>>>) -> bool + Clone {
+    |table_ready: Option<Res<StdbTableReady<T>>>| {
+        table_ready.is_some_and(|table_ready| table_ready.is_ready())
+    }
+}
@@ -1,12 +1,19 @@
 use std::{
-    any::TypeId,
-    sync::mpsc::{Sender, channel},
+    any::{TypeId, type_name},
+    marker::PhantomData,
+    sync::{
+        Arc,
+        mpsc::{Sender, channel},
+    },
 };
 
-use bevy::app::App;
+use bevy::{app::App, prelude::Event};
 use spacetimedb_sdk::{__codegen as spacetime_codegen, Table, TableWithPrimaryKey};
 
-use crate::AddEventChannelAppExtensions;
+use crate::{
+    AddEventChannelAppExtensions, StdbEventMeta, StdbRowChange, connector::TableBinder,
+    event_meta::is_subscription_applied, mirror::RowMirrorEvent, transactions::RowEventSender,
+};
 // Imports are marked as unused but they are useful for linking types in docs.
 // #[allow(unused_imports)]
 use crate::{DeleteEvent, InsertEvent, InsertUpdateEvent, StdbPlugin, UpdateEvent};
@@ -45,6 +52,54 @@ impl TableEvents {
             delete: true,
         }
     }
+
+    /// Returns the events registered by either `self` or `other`.
+    pub(crate) fn merge(self, other: Self) -> Self {
+        Self {
+            insert: self.insert || other.insert,
+            update: self.update || other.update,
+            delete: self.delete || other.delete,
+        }
+    }
+}
+
+/// A table registered into the plugin, the later registrations of the same table are merged into it.
+pub(crate) struct TableRegistration<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> {
+    /// The type of the table accessor, identifying the table.
+    type_id: TypeId,
+    /// The name of the table accessor type.
+    pub(crate) name: &'static str,
+    /// The events requested by all the registrations of the table.
+    pub(crate) events: TableEvents,
+    /// How many times the table was registered, not counting the registrations of mirrored tables.
+    pub(crate) registrations: usize,
+    /// Sets up the requested events, and returns the callback binding them to a built connection.
+    #[allow(clippy::type_complexity)]
+    pub(crate) register:
+        Box<dyn Fn(&StdbPlugin<C, M>, &mut App, TableEvents) -> TableBinder<C> + Send + Sync>,
+}
+
+/// A function returning the handle of a table from the tables of a connection, e.g. `RemoteTables::players`.
+///
+/// The table handle borrows the tables of the connection, so it never outlives it.
+/// Closures cannot return a handle borrowing their argument, the generated `RemoteTables` methods are used instead.
+pub trait TableAccessor<'db, V: 'db> {
+    /// The table handle returned by the accessor.
+    type Table;
+
+    /// Returns the handle of the table.
+    fn table(&self, db: &'db V) -> Self::Table;
+}
+
+impl<'db, V: 'db, T, F: Fn(&'db V) -> T> TableAccessor<'db, V> for F {
+    type Table = T;
+
+    fn table(&self, db: &'db V) -> T {
+        self(db)
+    }
 }
 
 impl<
@@ -53,168 +108,303 @@ impl<
 > StdbPlugin<C, M>
 {
     /// Registers a table for the bevy application with all events enabled.
-    pub fn add_table<TRow, TTable, F>(self, accessor: F) -> Self
+    ///
+    /// The `accessor` is called each time a connection is built, usually it is one of the generated `RemoteTables` methods.
+    pub fn add_table<TRow, F>(self, accessor: F) -> Self
     where
         TRow: Send + Sync + Clone + 'static,
-        TTable: Table<Row = TRow> + TableWithPrimaryKey<Row = TRow>,
-        F: 'static + Send + Sync + Fn(&'static C::DbView) -> TTable,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
     {
         self.add_partial_table(accessor, TableEvents::all())
     }
 
     ///Registers a table for the bevy application with the specified events in the `events` parameter.
-    pub fn add_partial_table<TRow, TTable, F>(mut self, accessor: F, events: TableEvents) -> Self
+    ///
+    /// The `accessor` is called each time a connection is built, see [`TableAccessor`].
+    ///
+    /// Registering the same table more than once merges the requested events, and logs a warning when the plugin is built.
+    pub fn add_partial_table<TRow, F>(self, accessor: F, events: TableEvents) -> Self
+    where
+        TRow: Send + Sync + Clone + 'static,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
+    {
+        self.register_partial_table(accessor, events, true)
+    }
+
+    /// Registers a table like [`Self::add_partial_table`], `counted` is `false` when it is only registered to be mirrored,
+    /// so that it is not reported as registered twice when it is also registered explicitly.
+    pub(crate) fn register_partial_table<TRow, F>(
+        mut self,
+        accessor: F,
+        events: TableEvents,
+        counted: bool,
+    ) -> Self
     where
         TRow: Send + Sync + Clone + 'static,
-        TTable: Table<Row = TRow> + TableWithPrimaryKey<Row = TRow>,
-        F: 'static + Send + Sync + Fn(&'static C::DbView) -> TTable,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
     {
-        // A closure that sets up events for the table
-        let register = move |plugin: &Self, app: &mut App, db: &'static C::DbView| {
-            let table = accessor(db);
-            if events.insert {
-                plugin.on_insert(app, &table);
-            }
-            if events.delete {
-                plugin.on_delete(app, &table);
-            }
-            if events.update {
-                plugin.on_update(app, &table);
-            }
-            if events.update && events.insert {
-                plugin.on_insert_update(app, &table);
-            }
+        let accessor = Arc::new(accessor);
+
+        // A closure that sets up events for the table, and returns the callback binding them to a built connection.
+        let register = move |plugin: &Self, app: &mut App, events: TableEvents| -> TableBinder<C> {
+            let on_insert = events
+                .insert
+                .then(|| plugin.row_event_binding::<InsertEvent<TRow>>(app));
+            let on_delete = events
+                .delete
+                .then(|| plugin.row_event_binding::<DeleteEvent<TRow>>(app));
+            let on_update = events
+                .update
+                .then(|| plugin.row_event_binding::<UpdateEvent<TRow>>(app));
+            let on_insert_update = (events.update && events.insert)
+                .then(|| plugin.row_event_binding::<InsertUpdateEvent<TRow>>(app));
+            let on_mirror = plugin
+                .mirrored_rows
+                .contains(&TypeId::of::<TRow>())
+                .then(|| plugin.row_event_binding::<RowMirrorEvent<TRow>>(app));
+            plugin.add_row_triggers::<TRow>(app);
+
+            let accessor = accessor.clone();
+            Box::new(move |db: &C::DbView| {
+                let table = accessor.table(db);
+                if let Some(on_insert) = &on_insert {
+                    on_insert.bind(&table);
+                }
+                if let Some(on_delete) = &on_delete {
+                    on_delete.bind(&table);
+                }
+                if let Some(on_update) = &on_update {
+                    on_update.bind(&table);
+                }
+                if let Some(on_insert_update) = &on_insert_update {
+                    on_insert_update.bind(&table);
+                }
+                if let Some(on_mirror) = &on_mirror {
+                    on_mirror.bind(&table);
+                }
+            })
         };
 
         // Store this table, and later when the plugin is built, call them on .
-        self.table_registers.push(Box::new(register));
+        self.register_table::<F>(events, counted, register);
 
         self
     }
 
-    /// Register a Bevy event of type InsertEvent<TRow> for the `on_insert` event on the provided table.
-    fn on_insert<TRow>(&self, app: &mut App, table: &impl Table<Row = TRow>) -> &Self
+    /// Registers a table without a primary key for the bevy application, only its insert and delete events are sent.
+    ///
+    /// The `accessor` is called each time a connection is built, see [`TableAccessor`].
+    pub fn add_table_without_pk<TRow, F>(mut self, accessor: F) -> Self
     where
         TRow: Send + Sync + Clone + 'static,
+        F: for<'db> TableAccessor<'db, C::DbView> + Send + Sync + 'static,
+        for<'db> <F as TableAccessor<'db, C::DbView>>::Table:
+            Table<Row = TRow, EventContext = M::EventContext>,
     {
-        let type_id = TypeId::of::<InsertEvent<TRow>>();
+        let accessor = Arc::new(accessor);
 
-        let mut map = self.event_senders.lock().unwrap();
+        // Without a primary key, only the insert and delete events can be registered.
+        let register = move |plugin: &Self, app: &mut App, _: TableEvents| -> TableBinder<C> {
+            let on_insert = plugin.row_event_binding::<InsertEvent<TRow>>(app);
+            let on_delete = plugin.row_event_binding::<DeleteEvent<TRow>>(app);
+            plugin.add_row_triggers::<TRow>(app);
 
-        let sender = map
-            .entry(type_id)
-            .or_insert_with(|| {
-                let (send, recv) = channel::<InsertEvent<TRow>>();
-                app.add_event_channel(recv);
-                Box::new(send)
+            let accessor = accessor.clone();
+            Box::new(move |db: &C::DbView| {
+                let table = accessor.table(db);
+                on_insert.bind(&table);
+                on_delete.bind(&table);
             })
-            .downcast_ref::<Sender<InsertEvent<TRow>>>()
-            .expect("Sender type mismatch")
-            .clone();
+        };
 
-        table.on_insert(move |_ctx, row| {
-            let event = InsertEvent { row: row.clone() };
-            let _ = sender.send(event);
-        });
+        self.register_table::<F>(TableEvents::no_update(), true, register);
 
         self
     }
 
-    /// Register a Bevy event of type DeleteEvent<TRow> for the `on_delete` event on the provided table.
-    fn on_delete<TRow>(&self, app: &mut App, table: &impl Table<Row = TRow>) -> &Self
-    where
-        TRow: Send + Sync + Clone + 'static,
-    {
-        let type_id = TypeId::of::<DeleteEvent<TRow>>();
+    /// Stores the registration of the table returned by the accessor `F`, merging the events if it was already registered.
+    fn register_table<F: 'static>(
+        &mut self,
+        events: TableEvents,
+        counted: bool,
+        register: impl Fn(&Self, &mut App, TableEvents) -> TableBinder<C> + Send + Sync + 'static,
+    ) {
+        let type_id = TypeId::of::<F>();
+
+        // Registering it twice would send every row event twice.
+        if let Some(table) = self
+            .table_registers
+            .iter_mut()
+            .find(|table| table.type_id == type_id)
+        {
+            table.events = table.events.merge(events);
+            table.registrations += usize::from(counted);
+            return;
+        }
+
+        self.table_registers.push(TableRegistration {
+            type_id,
+            name: type_name::<F>(),
+            events,
+            registrations: usize::from(counted),
+            register: Box::new(register),
+        });
+    }
+
+    /// Returns the sender of the Bevy event `E`, registering the event channel the first time it is requested.
+    pub(crate) fn event_sender<E: Event>(&self, app: &mut App) -> Sender<E> {
+        let type_id = TypeId::of::<E>();
 
         let mut map = self.event_senders.lock().unwrap();
-        let sender = map
-            .entry(type_id)
+        map.entry(type_id)
             .or_insert_with(|| {
-                let (send, recv) = channel::<DeleteEvent<TRow>>();
+                let (send, recv) = channel::<E>();
                 app.add_event_channel(recv);
                 Box::new(send)
             })
-            .downcast_ref::<Sender<DeleteEvent<TRow>>>()
+            .downcast_ref::<Sender<E>>()
             .expect("Sender type mismatch")
-            .clone();
+            .clone()
+    }
+
+    /// Registers the Bevy row event `E`, and returns the binding forwarding the callbacks of a table to it.
+    fn row_event_binding<E: Event>(&self, app: &mut App) -> RowEventBinding<E, M> {
+        RowEventBinding {
+            sender: self.row_event_sender::<E>(app),
+            event_meta: self.event_meta,
+            _module: PhantomData,
+        }
+    }
+}
+
+/// Forwards the callbacks of the table handles of each built connection to the Bevy row event `E`.
+struct RowEventBinding<E: Event, M> {
+    sender: RowEventSender<E>,
+    event_meta: bool,
+    _module: PhantomData<fn() -> M>,
+}
 
-        table.on_delete(move |_ctx, row| {
-            let event = DeleteEvent { row: row.clone() };
-            let _ = sender.send(event);
+impl<TRow: Send + Sync + Clone + 'static, M: spacetime_codegen::SpacetimeModule>
+    RowEventBinding<InsertEvent<TRow>, M>
+{
+    /// Forwards the `on_insert` event of the table.
+    fn bind<TTable: Table<Row = TRow, EventContext = M::EventContext>>(&self, table: &TTable) {
+        let sender = self.sender.clone();
+        let event_meta = self.event_meta;
+        table.on_insert(move |ctx, row| {
+            let event = InsertEvent {
+                row: row.clone(),
+                initial: is_subscription_applied(ctx),
+                meta: event_meta.then(|| StdbEventMeta::from_context(ctx)),
+            };
+            sender.send(event, Some(StdbRowChange::insert(row.clone())));
         });
+    }
+}
 
-        self
+impl<TRow: Send + Sync + Clone + 'static, M: spacetime_codegen::SpacetimeModule>
+    RowEventBinding<DeleteEvent<TRow>, M>
+{
+    /// Forwards the `on_delete` event of the table.
+    fn bind<TTable: Table<Row = TRow, EventContext = M::EventContext>>(&self, table: &TTable) {
+        let sender = self.sender.clone();
+        let event_meta = self.event_meta;
+        table.on_delete(move |ctx, row| {
+            let event = DeleteEvent {
+                row: row.clone(),
+                meta: event_meta.then(|| StdbEventMeta::from_context(ctx)),
+            };
+            sender.send(event, Some(StdbRowChange::delete(row.clone())));
+        });
     }
+}
 
-    /// Register a Bevy event of type UpdateEvent<TRow> for the `on_update` event on the provided table.
-    fn on_update<TRow, TTable>(&self, app: &mut App, table: &TTable) -> &Self
+impl<TRow: Send + Sync + Clone + 'static, M: spacetime_codegen::SpacetimeModule>
+    RowEventBinding<UpdateEvent<TRow>, M>
+{
+    /// Forwards the `on_update` event of the table.
+    fn bind<TTable>(&self, table: &TTable)
     where
-        TRow: Send + Sync + Clone + 'static,
-        TTable: Table<Row = TRow> + TableWithPrimaryKey<Row = TRow>,
+        TTable: Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
     {
-        let type_id = TypeId::of::<UpdateEvent<TRow>>();
-
-        let mut map = self.event_senders.lock().unwrap();
-        let sender = map
-            .entry(type_id)
-            .or_insert_with(|| {
-                let (send, recv) = channel::<UpdateEvent<TRow>>();
-                app.add_event_channel(recv);
-                Box::new(send)
-            })
-            .downcast_ref::<Sender<UpdateEvent<TRow>>>()
-            .expect("Sender type mismatch")
-            .clone();
-
-        table.on_update(move |_ctx, old, new| {
+        let sender = self.sender.clone();
+        let event_meta = self.event_meta;
+        table.on_update(move |ctx, old, new| {
             let event = UpdateEvent {
                 old: old.clone(),
                 new: new.clone(),
+                meta: event_meta.then(|| StdbEventMeta::from_context(ctx)),
             };
-            let _ = sender.send(event);
+            sender.send(event, Some(StdbRowChange::update(old.clone(), new.clone())));
         });
-
-        self
     }
+}
 
-    /// Register a Bevy event of type InsertUpdateEvent<TRow> for the `on_insert` and `on_update` events on the provided table.
-    fn on_insert_update<TRow, TTable>(&self, app: &mut App, table: &TTable) -> &Self
+impl<TRow: Send + Sync + Clone + 'static, M: spacetime_codegen::SpacetimeModule>
+    RowEventBinding<InsertUpdateEvent<TRow>, M>
+{
+    /// Forwards the `on_insert` and `on_update` events of the table.
+    fn bind<TTable>(&self, table: &TTable)
     where
-        TRow: Send + Sync + Clone + 'static,
-        TTable: Table<Row = TRow> + TableWithPrimaryKey<Row = TRow>,
+        TTable: Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
     {
-        let type_id = TypeId::of::<InsertUpdateEvent<TRow>>();
-
-        let mut map = self.event_senders.lock().unwrap();
-        let send = map
-            .entry(type_id)
-            .or_insert_with(|| {
-                let (send, recv) = channel::<InsertUpdateEvent<TRow>>();
-                app.add_event_channel(recv);
-                Box::new(send)
-            })
-            .downcast_ref::<Sender<InsertUpdateEvent<TRow>>>()
-            .expect("Sender type mismatch")
-            .clone();
+        let event_meta = self.event_meta;
 
-        let send_update = send.clone();
-        table.on_update(move |_ctx, old, new| {
+        let send_update = self.sender.clone();
+        table.on_update(move |ctx, old, new| {
             let event = InsertUpdateEvent {
                 old: Some(old.clone()),
                 new: new.clone(),
+                initial: false,
+                meta: event_meta.then(|| StdbEventMeta::from_context(ctx)),
             };
-            let _ = send_update.send(event);
+            send_update.send(event, None);
         });
 
-        table.on_insert(move |_ctx, row| {
+        let send_insert = self.sender.clone();
+        table.on_insert(move |ctx, row| {
             let event = InsertUpdateEvent {
                 old: None,
                 new: row.clone(),
+                initial: is_subscription_applied(ctx),
+                meta: event_meta.then(|| StdbEventMeta::from_context(ctx)),
+            };
+            send_insert.send(event, None);
+        });
+    }
+}
+
+impl<TRow: Send + Sync + Clone + 'static, M: spacetime_codegen::SpacetimeModule>
+    RowEventBinding<RowMirrorEvent<TRow>, M>
+{
+    /// Forwards the `on_insert`, `on_update` and `on_delete` events of the table, in the order they are received.
+    fn bind<TTable>(&self, table: &TTable)
+    where
+        TTable: Table<Row = TRow, EventContext = M::EventContext> + TableWithPrimaryKey<Row = TRow>,
+    {
+        let send_insert = self.sender.clone();
+        table.on_insert(move |_, row| {
+            send_insert.send(RowMirrorEvent::Insert(row.clone()), None);
+        });
+
+        let send_update = self.sender.clone();
+        table.on_update(move |_, old, new| {
+            let event = RowMirrorEvent::Update {
+                old: old.clone(),
+                new: new.clone(),
             };
-            let _ = send.send(event);
+            send_update.send(event, None);
         });
 
-        self
+        let send_delete = self.sender.clone();
+        table.on_delete(move |_, row| {
+            send_delete.send(RowMirrorEvent::Delete(row.clone()), None);
+        });
     }
 }
@@ -0,0 +1,279 @@
+use std::{
+    any::{Any, TypeId},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+};
+
+use bevy::{
+    app::{App, PreUpdate},
+    ecs::schedule::IntoScheduleConfigs,
+    prelude::*,
+};
+use spacetimedb_sdk::__codegen as spacetime_codegen;
+
+use crate::{StdbPlugin, channel_receiver::EventChannelSet};
+
+/// The kind of a [`StdbRowChange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowChangeKind {
+    /// A row was inserted.
+    Insert,
+    /// A row was updated.
+    Update,
+    /// A row was deleted.
+    Delete,
+}
+
+/// A row change of a [`StdbTransactionEvent`].
+#[derive(Clone)]
+pub struct StdbRowChange {
+    kind: RowChangeKind,
+    old: Option<Arc<dyn Any + Send + Sync>>,
+    row: Arc<dyn Any + Send + Sync>,
+}
+
+impl StdbRowChange {
+    pub(crate) fn insert<T: Send + Sync + 'static>(row: T) -> Self {
+        Self {
+            kind: RowChangeKind::Insert,
+            old: None,
+            row: Arc::new(row),
+        }
+    }
+
+    pub(crate) fn update<T: Send + Sync + 'static>(old: T, new: T) -> Self {
+        Self {
+            kind: RowChangeKind::Update,
+            old: Some(Arc::new(old)),
+            row: Arc::new(new),
+        }
+    }
+
+    pub(crate) fn delete<T: Send + Sync + 'static>(row: T) -> Self {
+        Self {
+            kind: RowChangeKind::Delete,
+            old: None,
+            row: Arc::new(row),
+        }
+    }
+
+    /// Returns whether the row was inserted, updated or deleted.
+    pub fn kind(&self) -> RowChangeKind {
+        self.kind
+    }
+
+    /// Returns `true` if the row is of type `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        (*self.row).type_id() == TypeId::of::<T>()
+    }
+
+    /// Returns the inserted, new or deleted row if it is of type `T`.
+    pub fn row<T: 'static>(&self) -> Option<&T> {
+        self.row.downcast_ref()
+    }
+
+    /// Returns the old row of an update if it is of type `T`.
+    pub fn old_row<T: 'static>(&self) -> Option<&T> {
+        self.old.as_ref()?.downcast_ref()
+    }
+}
+
+impl std::fmt::Debug for StdbRowChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdbRowChange")
+            .field("kind", &self.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An event that is sent with all the row changes of a transaction,
+/// when the plugin is built with [`StdbPlugin::with_transaction_events`].
+#[derive(Event, Debug, Clone)]
+pub struct StdbTransactionEvent {
+    /// The row changes of the registered tables, in the order they were received.
+    pub changes: Vec<StdbRowChange>,
+}
+
+impl StdbTransactionEvent {
+    /// Iterates over the inserted rows of type `T`.
+    pub fn inserted<T: 'static>(&self) -> impl Iterator<Item = &T> {
+        self.rows(RowChangeKind::Insert)
+            .filter_map(|change| change.row())
+    }
+
+    /// Iterates over the old and new rows of type `T` that were updated.
+    pub fn updated<T: 'static>(&self) -> impl Iterator<Item = (&T, &T)> {
+        self.rows(RowChangeKind::Update)
+            .filter_map(|change| Some((change.old_row()?, change.row()?)))
+    }
+
+    /// Iterates over the deleted rows of type `T`.
+    pub fn deleted<T: 'static>(&self) -> impl Iterator<Item = &T> {
+        self.rows(RowChangeKind::Delete)
+            .filter_map(|change| change.row())
+    }
+
+    fn rows(&self, kind: RowChangeKind) -> impl Iterator<Item = &StdbRowChange> {
+        self.changes
+            .iter()
+            .filter(move |change| change.kind == kind)
+    }
+}
+
+/// Writes a row event into the world.
+type WriteEvent = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// A message sent from the SDK callbacks to the [`deliver_transactions`] system.
+pub(crate) enum TransactionMessage {
+    /// Writes a row event into the world.
+    Event(WriteEvent),
+    /// A row change of the transaction being received.
+    Change(StdbRowChange),
+    /// The end of a message of the server, the events received until then are delivered.
+    Commit,
+}
+
+/// The sender of a row or subscription event, either through its own channel or through the transaction channel.
+pub(crate) enum RowEventSender<E> {
+    Channel(Sender<E>),
+    Transaction(Sender<TransactionMessage>),
+}
+
+impl<E> Clone for RowEventSender<E> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Channel(sender) => Self::Channel(sender.clone()),
+            Self::Transaction(sender) => Self::Transaction(sender.clone()),
+        }
+    }
+}
+
+impl<E: Event> RowEventSender<E> {
+    /// Sends the event, and the row change if it is part of a transaction.
+    pub(crate) fn send(&self, event: E, change: Option<StdbRowChange>) {
+        match self {
+            Self::Channel(sender) => {
+                let _ = sender.send(event);
+            }
+            Self::Transaction(sender) => {
+                let _ = sender.send(TransactionMessage::Event(Box::new(move |world| {
+                    world.send_event(event);
+                })));
+                if let Some(change) = change {
+                    let _ = sender.send(TransactionMessage::Change(change));
+                }
+            }
+        }
+    }
+}
+
+/// The receiving end of the transaction channel.
+#[derive(Resource)]
+struct TransactionReceiver {
+    receiver: Mutex<Receiver<TransactionMessage>>,
+    /// Whether everything received is delivered each frame, when the run mode does not mark the end of the messages.
+    commit_each_frame: bool,
+    /// The events and changes received since the last commit.
+    pending_events: Vec<WriteEvent>,
+    pending_changes: Vec<StdbRowChange>,
+}
+
+impl<
+    C: spacetime_codegen::DbConnection<Module = M> + spacetimedb_sdk::DbContext,
+    M: spacetime_codegen::SpacetimeModule<DbConnection = C>,
+> StdbPlugin<C, M>
+{
+    /// Sets whether the row events of a transaction are delivered together, along with a [`StdbTransactionEvent`].
+    ///
+    /// The events received while processing a message of the server are held back until the whole message is processed,
+    /// so they are all delivered in the same frame and in the order they were received.
+    /// The [`SubscriptionAppliedEvent`](crate::SubscriptionAppliedEvent) and [`SubscriptionErrorEvent`](crate::SubscriptionErrorEvent)
+    /// are delivered in the same order, so a subscription is applied in the frame its initial rows are inserted, not before.
+    ///
+    /// What is guaranteed depends on the run mode:
+    /// - [`StdbRunMode::FrameTick`](crate::StdbRunMode::FrameTick): the messages processed during a frame are delivered in that frame,
+    ///   one [`StdbTransactionEvent`] per frame.
+    /// - [`StdbRunMode::Messages`](crate::StdbRunMode::Messages) and [`StdbRunMode::AsyncMessages`](crate::StdbRunMode::AsyncMessages):
+    ///   each message is delivered whole in the first frame after it is processed, with its own [`StdbTransactionEvent`].
+    /// - [`StdbRunMode::Threaded`](crate::StdbRunMode::Threaded) and [`StdbRunMode::Async`](crate::StdbRunMode::Async):
+    ///   the end of a message is not known, so the events received are delivered each frame and a message can be split over two frames.
+    ///   Only their order is kept, and a warning is logged when the plugin is built.
+    pub fn with_transaction_events(mut self, transaction_events: bool) -> Self {
+        self.transaction_events = transaction_events;
+        self
+    }
+
+    /// Returns the sender of the row or subscription event `E`, through the transaction channel if transaction events are enabled.
+    pub(crate) fn row_event_sender<E: Event>(&self, app: &mut App) -> RowEventSender<E> {
+        match self.transaction_sender(app) {
+            Some(sender) => {
+                app.add_event::<E>();
+                RowEventSender::Transaction(sender)
+            }
+            None => RowEventSender::Channel(self.event_sender::<E>(app)),
+        }
+    }
+
+    /// Returns the sender of the transaction channel if transaction events are enabled,
+    /// registering the channel the first time it is requested.
+    pub(crate) fn transaction_sender(&self, app: &mut App) -> Option<Sender<TransactionMessage>> {
+        if !self.transaction_events {
+            return None;
+        }
+
+        let mut sender = self.transaction_sender.lock().unwrap();
+        let sender = sender.get_or_insert_with(|| {
+            let (send, recv) = channel();
+            app.add_event::<StdbTransactionEvent>()
+                .insert_resource(TransactionReceiver {
+                    receiver: Mutex::new(recv),
+                    commit_each_frame: !self.run_mode.is_some_and(|mode| mode.commits()),
+                    pending_events: Vec::new(),
+                    pending_changes: Vec::new(),
+                })
+                .add_systems(PreUpdate, deliver_transactions.in_set(EventChannelSet));
+            send
+        });
+        Some(sender.clone())
+    }
+}
+
+/// Writes the row events of the committed transactions, and sends their [`StdbTransactionEvent`].
+fn deliver_transactions(world: &mut World) {
+    world.resource_scope(|world, mut transactions: Mut<TransactionReceiver>| {
+        // this should be the only system working with the receiver,
+        // thus we always expect to get this lock
+        let messages: Vec<_> = transactions
+            .receiver
+            .lock()
+            .expect("unable to acquire mutex lock")
+            .try_iter()
+            .collect();
+
+        for message in messages {
+            match message {
+                TransactionMessage::Event(write) => transactions.pending_events.push(write),
+                TransactionMessage::Change(change) => transactions.pending_changes.push(change),
+                TransactionMessage::Commit => commit(world, &mut transactions),
+            }
+        }
+
+        if transactions.commit_each_frame {
+            commit(world, &mut transactions);
+        }
+    });
+}
+
+/// Delivers the events and changes received since the last commit.
+fn commit(world: &mut World, transactions: &mut TransactionReceiver) {
+    for write in transactions.pending_events.drain(..) {
+        write(world);
+    }
+
+    if !transactions.pending_changes.is_empty() {
+        let changes = std::mem::take(&mut transactions.pending_changes);
+        world.send_event(StdbTransactionEvent { changes });
+    }
+}
@@ -1,33 +1,16 @@
 use bevy::{log::LogPlugin, prelude::*};
 use bevy_spacetimedb::{
-    ReadDeleteEvent, ReadInsertEvent, ReadInsertUpdateEvent, ReadReducerEvent,
-    ReadStdbConnectedEvent, ReadUpdateEvent, ReducerResultEvent, RegisterReducerEvent,
-    StdbConnection, StdbPlugin, TableEvents,
+    ReadDeleteEvent, ReadInsertEvent, ReadInsertUpdateEvent, ReadReducerEvent, ReadUpdateEvent,
+    StdbConnection, StdbPlugin, StdbState, StdbSubscriptions, StdbTableSynced, Subscribe,
+    SubscriptionAppliedEvent, SubscriptionErrorEvent, stdb_bindings,
 };
-use spacetimedb_sdk::ReducerEvent;
-use stdb::{DbConnection, Reducer};
+use stdb::DbConnection;
 
-use crate::stdb::gs_register_reducer::gs_register;
-use crate::stdb::gs_set_ready_reducer::gs_set_ready;
-use crate::stdb::{
-    GameServersTableAccess, PlanetsTableAccess, Player, PlayersTableAccess, RemoteModule,
-    RemoteReducers, RemoteTables,
-};
+use crate::stdb::{GameServer, Player};
 mod stdb;
 
-#[derive(Debug, RegisterReducerEvent)]
-#[allow(dead_code)]
-pub struct GsRegister {
-    event: ReducerEvent<Reducer>,
-    ip: String,
-    port: u16,
-}
-
-#[derive(Debug, RegisterReducerEvent)]
-#[allow(dead_code)]
-pub struct GsSetReady {
-    event: ReducerEvent<Reducer>,
-}
+// Generates the reducer events, and lets `add_all_tables` and `add_all_reducers` register everything.
+stdb_bindings!(crate::stdb);
 
 pub type SpacetimeDB<'a> = Res<'a, StdbConnection<DbConnection>>;
 
@@ -39,15 +22,17 @@ pub fn main() {
                 .with_uri("http://localhost:3000")
                 .with_module_name("chat")
                 .with_run_fn(DbConnection::run_threaded)
-                .add_table(RemoteTables::planets)
-                .add_table(RemoteTables::players)
-                .add_table(RemoteTables::game_servers)
-                .add_partial_table(RemoteTables::players, TableEvents::no_update()) // Some tables
-                // do not have update events, especially those without primary keys.
-                .add_reducer::<GsRegister>()
-                .add_reducer::<GsSetReady>(),
+                .with_state::<StdbState>()
+                .with_subscription([
+                    Subscribe::table::<Player>().filter(|player| player.online.eq(true))
+                ])
+                .with_subscription([Subscribe::table::<GameServer>()])
+                .add_all_tables()
+                .add_all_reducers(),
         )
-        .add_systems(Update, on_connected)
+        .add_systems(OnEnter(StdbState::Connected), on_connected)
+        .add_systems(Update, on_subscription_events)
+        .add_systems(Update, on_players_synced)
         .add_systems(Update, on_player_inserted)
         .add_systems(Update, on_player_updated)
         .add_systems(Update, on_player_deleted)
@@ -58,24 +43,40 @@ pub fn main() {
 }
 
 // SpacetimeDB is defined as an alias for the StdbConnection with DbConnection.
-fn on_connected(mut events: ReadStdbConnectedEvent, stdb: SpacetimeDB) {
-    for _ev in events.read() {
-        info!("Connected to SpacetimeDB");
+fn on_connected(stdb: SpacetimeDB) {
+    info!("Connected to SpacetimeDB as {}", stdb.identity());
+}
 
-        stdb.subscription_builder()
-            .on_applied(|_| info!("Subscription to lobby applied"))
-            .on_error(|_, err| error!("Subscription to lobby failed for: {}", err))
-            .subscribe("SELECT * FROM lobby");
+// The subscriptions are applied by the plugin on each connection.
+fn on_subscription_events(
+    subscriptions: Res<StdbSubscriptions>,
+    mut applied: EventReader<SubscriptionAppliedEvent>,
+    mut errors: EventReader<SubscriptionErrorEvent>,
+) {
+    for event in applied.read() {
+        info!(
+            "Subscription to {:?} applied",
+            subscriptions.queries(event.id)
+        );
+    }
+    for event in errors.read() {
+        error!(
+            "Subscription to {:?} failed for: {}",
+            subscriptions.queries(event.id),
+            event.err
+        );
+    }
+}
 
-        stdb.subscription_builder()
-            .on_applied(|_| info!("Subscription to user applied"))
-            .on_error(|_, err| error!("Subscription to user failed for: {}", err))
-            .subscribe("SELECT * FROM user");
+fn on_players_synced(mut events: EventReader<StdbTableSynced<Player>>) {
+    for _ in events.read() {
+        info!("Players synced");
     }
 }
 
 fn on_player_inserted(mut events: ReadInsertEvent<Player>) {
-    for event in events.read() {
+    // The players already online are received when the subscription is applied
+    for event in events.read().filter(|event| !event.initial) {
         // Row below is just an example, does not actually compile.
         // commands.spawn(Player { id: event.row.id });
         info!("Player inserted: {:?}", event.row);
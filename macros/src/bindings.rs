@@ -0,0 +1,288 @@
+use std::path::Path as FsPath;
+
+use heck::ToSnakeCase;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::{
+    Error, Fields, Ident, ImplItem, Item, Lit, LitStr, Path, TraitItem, Type, visit_mut::VisitMut,
+};
+
+use crate::reducer_events::{
+    PathResolver, module_dir, module_files, parse_module_file, reducer_event,
+};
+
+/// A table of the generated bindings.
+struct BindingsTable {
+    /// The `*TableAccess` trait providing the accessor.
+    access_trait: Ident,
+    /// The accessor method of `RemoteTables`.
+    accessor: Ident,
+    /// Whether the table handle implements `TableWithPrimaryKey`.
+    has_primary_key: bool,
+    /// The name of the table in the database.
+    name: String,
+    /// The row type of the table, as named in the bindings module.
+    row: Option<Ident>,
+}
+
+pub(crate) fn expand_bindings(module: Path) -> syn::Result<TokenStream> {
+    let module = &module;
+    let module_dir = module_dir(module)?;
+    let read_error = |err: std::io::Error| {
+        Error::new_spanned(
+            module,
+            format!("failed to read `{}`: {}", module_dir.display(), err),
+        )
+    };
+
+    let mut tracked_files = vec![module_dir.join("mod.rs")];
+
+    let mut tables = Vec::new();
+    for (_, file) in module_files(&module_dir, "_table.rs").map_err(read_error)? {
+        tables.push(bindings_table(&file)?);
+        tracked_files.push(file);
+    }
+
+    let mut reducer_names = Vec::new();
+    let mut reducer_events = Vec::new();
+    for (reducer, file) in module_files(&module_dir, "_reducer.rs").map_err(read_error)? {
+        let (name, event) = reducer_event(module, &reducer, &file)?;
+        reducer_names.push(name);
+        reducer_events.push(event);
+    }
+
+    let add_tables = tables.iter().map(|table| {
+        let access_trait = &table.access_trait;
+        let accessor = &table.accessor;
+        if table.has_primary_key {
+            quote! {
+                .add_table(<#module::RemoteTables as #module::#access_trait>::#accessor)
+            }
+        } else {
+            quote! {
+                .add_table_without_pk(<#module::RemoteTables as #module::#access_trait>::#accessor)
+            }
+        }
+    });
+
+    let mut table_rows = Vec::new();
+    let mut ready_rows = Vec::new();
+    for table in &tables {
+        let Some(row) = &table.row else {
+            continue;
+        };
+        // A row type shared by several tables can't tell which table to query
+        if tables
+            .iter()
+            .filter(|other| other.row.as_ref() == Some(row))
+            .count()
+            > 1
+        {
+            continue;
+        }
+        let file = module_dir.join(format!("{}_type.rs", row.to_string().to_snake_case()));
+        if let Some(table_row) = table_row(module, table, row, &file)? {
+            table_rows.push(table_row);
+            ready_rows.push(row);
+            tracked_files.push(file);
+        }
+    }
+
+    let tracked_files = tracked_files
+        .iter()
+        .filter(|file| file.is_file())
+        .map(|file| LitStr::new(&file.display().to_string(), Span::call_site()));
+
+    Ok(quote! {
+        #(#reducer_events)*
+
+        #(#table_rows)*
+
+        impl bevy_spacetimedb::StdbBindings<#module::DbConnection> for #module::RemoteModule {
+            fn add_all_tables(
+                plugin: bevy_spacetimedb::StdbPlugin<#module::DbConnection, Self>,
+            ) -> bevy_spacetimedb::StdbPlugin<#module::DbConnection, Self> {
+                plugin #(#add_tables)* #(.with_table_ready::<#module::#ready_rows>())*
+            }
+
+            fn add_all_reducers(
+                plugin: bevy_spacetimedb::StdbPlugin<#module::DbConnection, Self>,
+            ) -> bevy_spacetimedb::StdbPlugin<#module::DbConnection, Self> {
+                plugin #(.add_reducer::<#reducer_names>())*
+            }
+        }
+
+        // Recompile when the bindings are regenerated
+        #(const _: &[u8] = include_bytes!(#tracked_files);)*
+    })
+}
+
+/// Reads the accessor and primary key of a table from its generated file.
+fn bindings_table(file: &FsPath) -> syn::Result<BindingsTable> {
+    let ast = parse_module_file(file)?;
+
+    let access = ast.items.iter().find_map(|item| match item {
+        Item::Trait(item_trait) if item_trait.ident.to_string().ends_with("TableAccess") => {
+            let accessor = item_trait.items.iter().find_map(|item| match item {
+                TraitItem::Fn(method) => Some(method.sig.ident.clone()),
+                _ => None,
+            })?;
+            Some((item_trait.ident.clone(), accessor))
+        }
+        _ => None,
+    });
+    let Some((access_trait, accessor)) = access else {
+        return Err(Error::new(
+            Span::call_site(),
+            format!("no table access trait found in `{}`", file.display()),
+        ));
+    };
+
+    let has_primary_key = ast.items.iter().any(|item| match item {
+        Item::Impl(item_impl) => item_impl.trait_.as_ref().is_some_and(|(_, path, _)| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == "TableWithPrimaryKey")
+        }),
+        _ => false,
+    });
+
+    // The row is the `Row` type of the `Table` implementation
+    let row = ast.items.iter().find_map(|item| match item {
+        Item::Impl(item_impl)
+            if item_impl.trait_.as_ref().is_some_and(|(_, path, _)| {
+                path.segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "Table")
+            }) =>
+        {
+            item_impl.items.iter().find_map(|item| match item {
+                ImplItem::Type(ty) if ty.ident == "Row" => match &ty.ty {
+                    Type::Path(path) => path.path.get_ident().cloned(),
+                    _ => None,
+                },
+                _ => None,
+            })
+        }
+        _ => None,
+    });
+
+    // The name is given to `get_table` by the accessor, it defaults to the accessor name
+    let name = ast
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Impl(item_impl)
+                if item_impl.trait_.as_ref().is_some_and(|(_, path, _)| {
+                    path.segments
+                        .last()
+                        .is_some_and(|segment| segment.ident == access_trait)
+                }) =>
+            {
+                table_name(quote! { #item_impl }, false)
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| accessor.to_string());
+
+    Ok(BindingsTable {
+        access_trait,
+        accessor,
+        has_primary_key,
+        name,
+        row,
+    })
+}
+
+/// Finds the string literal passed to `get_table` in the tokens.
+fn table_name(tokens: TokenStream, mut after_get_table: bool) -> Option<String> {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) if ident == "get_table" => after_get_table = true,
+            TokenTree::Literal(literal) if after_get_table => {
+                if let Lit::Str(name) = Lit::new(literal) {
+                    return Some(name.value());
+                }
+            }
+            TokenTree::Group(group) => {
+                if let Some(name) = table_name(group.stream(), after_get_table) {
+                    return Some(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Implements `StdbTableRow` for the row of a table, along with the struct of its columns,
+/// returns `None` if the row struct isn't found in its generated file.
+fn table_row(
+    module: &Path,
+    table: &BindingsTable,
+    row: &Ident,
+    file: &FsPath,
+) -> syn::Result<Option<TokenStream>> {
+    if !file.is_file() {
+        return Ok(None);
+    }
+    let ast = parse_module_file(file)?;
+
+    let mut resolver = PathResolver::new(module);
+    for item in &ast.items {
+        if let Item::Use(item_use) = item {
+            resolver.collect(&item_use.tree, &mut Vec::new());
+        }
+    }
+
+    let fields = ast.items.iter().find_map(|item| match item {
+        Item::Struct(item_struct) if item_struct.ident == *row => match &item_struct.fields {
+            Fields::Named(fields) => Some(&fields.named),
+            _ => None,
+        },
+        _ => None,
+    });
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+
+    let span = Span::call_site();
+    let columns_name = Ident::new(&format!("{}Columns", row), span);
+    let table_name = LitStr::new(&table.name, span);
+    let doc = format!(
+        " The columns of the table `{}`, to filter typed subscription queries.",
+        table.name
+    );
+
+    let mut column_fields = Vec::new();
+    let mut column_values = Vec::new();
+    for field in fields {
+        let ident = &field.ident;
+        let mut ty = field.ty.clone();
+        resolver.visit_type_mut(&mut ty);
+        let name = ident
+            .as_ref()
+            .map(|ident| LitStr::new(&ident.to_string(), ident.span()));
+        column_fields.push(quote! {
+            pub #ident: bevy_spacetimedb::StdbColumn<#module::#row, #ty>,
+        });
+        column_values.push(quote! {
+            #ident: bevy_spacetimedb::StdbColumn::new(#name),
+        });
+    }
+
+    Ok(Some(quote! {
+        #[doc = #doc]
+        pub struct #columns_name {
+            #(#column_fields)*
+        }
+
+        impl bevy_spacetimedb::StdbTableRow for #module::#row {
+            const TABLE_NAME: &'static str = #table_name;
+            type Columns = #columns_name;
+            const COLUMNS: Self::Columns = #columns_name {
+                #(#column_values)*
+            };
+        }
+    }))
+}
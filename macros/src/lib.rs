@@ -1,7 +1,18 @@
-use heck::ToSnakeCase;
+use heck::{ToSnakeCase, ToUpperCamelCase};
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{Data, DeriveInput, Fields, Ident, parse_macro_input};
+use syn::{
+    Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitStr, Path, PathArguments, Type,
+    parse_macro_input,
+};
+
+mod bindings;
+mod reducer_events;
+mod row_bundle;
+
+use bindings::expand_bindings;
+use reducer_events::{ReducerEventsInput, expand_reducer_events};
+use row_bundle::expand_row_bundle;
 
 /// This macro automatically generates the boilerplate code needed to register a reducer
 /// with the `StdbPlugin`.
@@ -9,68 +20,284 @@ use syn::{Data, DeriveInput, Fields, Ident, parse_macro_input};
 /// ## Requirements
 ///
 /// - The struct must have exactly one field named `event` of type `ReducerEvent<Reducer>`
-/// - All other fields must match the reducer's parameter types and order
+/// - All other fields must match the reducer's parameter types and order,
+///   or be named after the parameters when `#[stdb(arg = "...")]` is used
 /// - Struct fields must be named (no tuple structs)
 ///
+/// ## Attributes
+///
+/// - `#[stdb(module = path::to::bindings)]`: the module containing the generated bindings.
+///   When set, the generated code only uses fully-qualified paths, otherwise `DbConnection`, `RemoteModule`,
+///   `RemoteReducers` and the reducer's trait must be in scope.
+/// - `#[stdb(reducer = "set_name")]`: the name of the reducer, defaults to the struct name in snake case.
+/// - `#[stdb(arg = "name")]` on a field: the name of the reducer's parameter. When any field has it,
+///   the fields are bound to the parameters by name rather than by position, and default to the field name.
+///
 /// ## Example
 ///
 ///```no-run
 /// #[derive(RegisterReducerEvent)]
-/// pub struct SetName {
+/// #[stdb(module = crate::stdb, reducer = "set_name")]
+/// pub struct NameChanged {
 ///     pub event: ReducerEvent<Reducer>,
-///     pub name: String,
+///     #[stdb(arg = "name")]
+///     pub new_name: String,
 /// }
 /// ```
-#[proc_macro_derive(RegisterReducerEvent)]
+#[proc_macro_derive(RegisterReducerEvent, attributes(stdb))]
 pub fn register_reducer_event_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand_register_reducer_event(&input).unwrap_or_else(|err| err.to_compile_error().into())
+}
+
+/// This macro implements `StdbRowBundle` for a bundle, so that a table can be mirrored as entities
+/// with `add_table_as_bundle`, each field of the bundle being a component built from the row.
+///
+/// ## Requirements
+///
+/// - The struct must also derive `Bundle`, and its fields must be named
+/// - The row fields must implement `Clone` and `PartialEq`
+///
+/// ## Attributes
+///
+/// - `#[stdb(row = Type)]`: the row of the table, required.
+/// - `#[stdb(field = "name")]` on a field: the field of the row the component is converted from with `From`,
+///   defaults to the field name. The component is only inserted again when the row field changes.
+/// - `#[stdb(with = path::to::function)]` on a field: a function returning the component from the row,
+///   called on every update of the row.
+///
+/// ## Example
+///
+///```no-run
+/// #[derive(Bundle, StdbRowBundle)]
+/// #[stdb(row = Player)]
+/// pub struct PlayerBundle {
+///     pub name: PlayerName,
+///     #[stdb(field = "pos")]
+///     pub position: Position,
+///     #[stdb(with = player_transform)]
+///     pub transform: Transform,
+/// }
+/// ```
+#[proc_macro_derive(StdbRowBundle, attributes(stdb))]
+pub fn stdb_row_bundle_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_row_bundle(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// This macro generates the event struct of every reducer of the generated bindings,
+/// so they don't have to be written and kept in sync by hand.
+///
+/// For each reducer, it reads the `<reducer>_reducer.rs` file of the bindings module and emits a struct
+/// named after its arguments (e.g. `GsRegister` for `GsRegisterArgs`) deriving [`RegisterReducerEvent`].
+///
+/// ## Requirements
+///
+/// - The bindings module must be given as a path from the crate root, its folder must be `src/<path>`
+/// - `spacetimedb_sdk` must be a dependency of the crate
+///
+/// ## Example
+///
+///```no-run
+/// // Every reducer of the module
+/// stdb_reducer_events!(crate::stdb);
+///
+/// // Only the listed reducers
+/// stdb_reducer_events!(crate::stdb, [gs_register, gs_set_ready]);
+/// ```
+#[proc_macro]
+pub fn stdb_reducer_events(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ReducerEventsInput);
+    expand_reducer_events(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// This macro implements `StdbBindings` for the generated bindings,
+/// so that all their tables and reducers can be registered with `add_all_tables` and `add_all_reducers`.
+///
+/// It reads the `*_table.rs` and `*_reducer.rs` files of the bindings module, and also generates the event
+/// struct of every reducer like [`stdb_reducer_events!`].
+/// Tables without a primary key are registered with `add_table_without_pk`.
+///
+/// The row of each table implements `StdbTableRow`, with a `<Row>Columns` struct for typed subscription queries,
+/// and `add_all_tables` tracks whether the table is ready with `with_table_ready`.
+/// Rows shared by several tables are left out.
+///
+/// ## Requirements
+///
+/// - The bindings module must be given as a path from the crate root, its folder must be `src/<path>`
+/// - `spacetimedb_sdk` must be a dependency of the crate
+///
+/// ## Example
+///
+///```no-run
+/// stdb_bindings!(crate::stdb);
+///
+/// StdbPlugin::default()
+///     .add_all_tables()
+///     .add_all_reducers();
+/// ```
+#[proc_macro]
+pub fn stdb_bindings(input: TokenStream) -> TokenStream {
+    let module = parse_macro_input!(input with Path::parse_mod_style);
+    expand_bindings(module)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_register_reducer_event(input: &DeriveInput) -> syn::Result<TokenStream> {
     let struct_name = &input.ident;
-    let struct_name_str = struct_name.to_string();
 
-    // Derive callback name directly from struct name (no suffix stripping)
-    let function_name = Ident::new(
-        &format!("on_{}", struct_name_str.to_snake_case()),
-        struct_name.span(),
+    // Path to the generated bindings and name of the reducer, if specified
+    let mut module: Option<Path> = None;
+    let mut reducer: Option<LitStr> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("stdb"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("module") {
+                module = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("reducer") {
+                reducer = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported stdb attribute, expected `module` or `reducer`"))
+            }
+        })?;
+    }
+
+    // Derive callback name from the reducer name, or directly from struct name (no suffix stripping)
+    let reducer_name = match &reducer {
+        Some(reducer) => parse_ident(reducer)?,
+        None => Ident::new(&struct_name.to_string().to_snake_case(), struct_name.span()),
+    };
+    let function_name = Ident::new(&format!("on_{}", reducer_name), reducer_name.span());
+    let variant_name = Ident::new(
+        &reducer_name.to_string().to_upper_camel_case(),
+        reducer_name.span(),
     );
 
     // Extract named fields
-    let fields = match input.data {
-        Data::Struct(data_struct) => match data_struct.fields {
-            Fields::Named(fields_named) => fields_named.named,
-            _ => panic!("Struct must have named fields"),
+    let fields = match &input.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => &fields_named.named,
+            Fields::Unnamed(fields_unnamed) => {
+                return Err(Error::new_spanned(
+                    fields_unnamed,
+                    "RegisterReducerEvent requires named fields, tuple structs are not supported",
+                ));
+            }
+            Fields::Unit => {
+                return Err(Error::new_spanned(
+                    struct_name,
+                    "RegisterReducerEvent requires a struct with an `event` field",
+                ));
+            }
         },
-        _ => panic!("Only structs are supported"),
+        Data::Enum(data_enum) => {
+            return Err(Error::new_spanned(
+                data_enum.enum_token,
+                "RegisterReducerEvent can only be derived for structs",
+            ));
+        }
+        Data::Union(data_union) => {
+            return Err(Error::new_spanned(
+                data_union.union_token,
+                "RegisterReducerEvent can only be derived for structs",
+            ));
+        }
     };
 
     // Separate 'event' field from reducer parameters
     let mut event_field = None;
-    let mut param_fields = Vec::new();
     let mut param_idents = Vec::new();
+    let mut arg_idents = Vec::new();
+    let mut renamed = false;
 
     for field in fields {
-        let field_ident = field.ident.as_ref().expect("Field must have identifier");
+        let Some(field_ident) = field.ident.as_ref() else {
+            return Err(Error::new_spanned(field, "field must have an identifier"));
+        };
         if field_ident == "event" {
             if event_field.is_some() {
-                panic!("Duplicate 'event' field");
+                return Err(Error::new_spanned(field_ident, "duplicate `event` field"));
             }
             event_field = Some(field);
         } else {
+            let arg = arg_name(&field.attrs)?;
+            renamed |= arg.is_some();
+            arg_idents.push(arg.unwrap_or_else(|| field_ident.clone()));
             param_idents.push(field_ident.clone());
-            param_fields.push(field);
         }
     }
 
-    if event_field.is_none() {
-        panic!("Struct must have an 'event' field");
-    }
+    let Some(event_field) = event_field else {
+        return Err(Error::new_spanned(
+            struct_name,
+            "RegisterReducerEvent requires an `event` field of type `ReducerEvent<Reducer>`",
+        ));
+    };
+    let Some(reducer_enum) = reducer_event_enum(&event_field.ty) else {
+        return Err(Error::new_spanned(
+            &event_field.ty,
+            "the `event` field must be of type `ReducerEvent<Reducer>`",
+        ));
+    };
+
+    // Use fully-qualified paths when the bindings module is known
+    let (db_connection, remote_module, remote_reducers, on_reducer, receiver) = match &module {
+        Some(module) => (
+            quote! { #module::DbConnection },
+            quote! { #module::RemoteModule },
+            quote! { #module::RemoteReducers },
+            quote! { <#module::RemoteReducers as #module::#reducer_name>::#function_name },
+            quote! { reducers, },
+        ),
+        None => (
+            quote! { DbConnection },
+            quote! { RemoteModule },
+            quote! { RemoteReducers },
+            quote! { reducers.#function_name },
+            quote! {},
+        ),
+    };
+
+    // Bind the arguments by position, or by name once a field is renamed
+    let (params, bind_args) = if renamed {
+        let ignored_params = param_idents.iter().map(|_| quote! { _ });
+        let arg_patterns = arg_idents.iter().zip(&param_idents).map(|(arg, param)| {
+            if arg == param {
+                quote! { #param }
+            } else {
+                quote! { #arg: #param }
+            }
+        });
+        (
+            quote! { #(#ignored_params),* },
+            quote! {
+                let #reducer_enum::#variant_name { #(#arg_patterns,)* .. } = &ctx.event.reducer else {
+                    unreachable!()
+                };
+            },
+        )
+    } else {
+        (quote! { #(#param_idents),* }, quote! {})
+    };
 
     // Generate the implementation
     let expanded = quote! {
-        impl bevy_spacetimedb::RegisterableReducerEvent<DbConnection, RemoteModule> for #struct_name {
-            fn set_stdb_callback(reducers: &RemoteReducers, sender: std::sync::mpsc::Sender<ReducerResultEvent<Self>>) {
-                reducers.#function_name(move |ctx, #(#param_idents),*| {
+        impl bevy_spacetimedb::RegisterableReducerEvent<#db_connection, #remote_module> for #struct_name {
+            fn set_stdb_callback(reducers: &#remote_reducers, sender: std::sync::mpsc::Sender<bevy_spacetimedb::ReducerResultEvent<Self>>) {
+                #on_reducer(#receiver move |ctx, #params| {
+                    #bind_args
                     sender
-                        .send(ReducerResultEvent::new(#struct_name {
+                        .send(bevy_spacetimedb::ReducerResultEvent::new(#struct_name {
                             event: ctx.event.clone(),
                             #(#param_idents: #param_idents.clone()),*
                         }))
@@ -80,5 +307,52 @@ pub fn register_reducer_event_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    TokenStream::from(expanded)
+    Ok(TokenStream::from(expanded))
+}
+
+/// Returns the name of the reducer's parameter bound to a field, if renamed with a `#[stdb(arg = "...")]` attribute.
+fn arg_name(attrs: &[syn::Attribute]) -> syn::Result<Option<Ident>> {
+    let mut arg = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("stdb")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("arg") {
+                let name: LitStr = meta.value()?.parse()?;
+                arg = Some(parse_ident(&name)?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported stdb attribute, expected `arg`"))
+            }
+        })?;
+    }
+    Ok(arg)
+}
+
+/// Parses the identifier named by a string attribute, e.g. `"set_name"`, with an error spanned to the string.
+fn parse_ident(name: &LitStr) -> syn::Result<Ident> {
+    name.parse().map_err(|_| {
+        Error::new(
+            name.span(),
+            format!("`{}` is not a valid identifier", name.value()),
+        )
+    })
+}
+
+/// Returns the path of `Reducer` if the type looks like `ReducerEvent<Reducer>`, optionally with path prefixes.
+fn reducer_event_enum(ty: &Type) -> Option<&Path> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "ReducerEvent" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(Type::Path(reducer)) if reducer.qself.is_none() => {
+            Some(&reducer.path)
+        }
+        _ => None,
+    }
 }
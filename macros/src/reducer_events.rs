@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path as FsPath, PathBuf},
+};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    Error, Fields, Ident, Item, LitStr, Path, Token, TypePath, UseTree, bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+};
+
+/// The input of `stdb_reducer_events!`: the bindings module and an optional list of reducers.
+pub(crate) struct ReducerEventsInput {
+    module: Path,
+    reducers: Option<Punctuated<Ident, Token![,]>>,
+}
+
+impl Parse for ReducerEventsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let module = Path::parse_mod_style(input)?;
+        let mut reducers = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let content;
+            bracketed!(content in input);
+            reducers = Some(content.parse_terminated(Ident::parse, Token![,])?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self { module, reducers })
+    }
+}
+
+pub(crate) fn expand_reducer_events(input: ReducerEventsInput) -> syn::Result<TokenStream> {
+    let module = &input.module;
+    let module_dir = module_dir(module)?;
+
+    // Either the listed reducers, or every reducer file of the module
+    let files = match &input.reducers {
+        Some(reducers) => reducers
+            .iter()
+            .map(|reducer| {
+                let file = module_dir.join(format!("{}_reducer.rs", reducer));
+                if file.is_file() {
+                    Ok((reducer.to_string(), file))
+                } else {
+                    Err(Error::new_spanned(
+                        reducer,
+                        format!("no reducer file found at `{}`", file.display()),
+                    ))
+                }
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        None => module_files(&module_dir, "_reducer.rs").map_err(|err| {
+            Error::new_spanned(
+                module,
+                format!("failed to read `{}`: {}", module_dir.display(), err),
+            )
+        })?,
+    };
+
+    let events = files
+        .iter()
+        .map(|(reducer, file)| reducer_event(module, reducer, file).map(|(_, event)| event))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! { #(#events)* })
+}
+
+/// Resolves the directory of the bindings module, relative to the `src` folder of the crate.
+pub(crate) fn module_dir(module: &Path) -> syn::Result<PathBuf> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| Error::new_spanned(module, "CARGO_MANIFEST_DIR is not set"))?;
+
+    let mut dir = PathBuf::from(manifest_dir).join("src");
+    for (i, segment) in module.segments.iter().enumerate() {
+        if i == 0 && segment.ident == "crate" {
+            continue;
+        }
+        if segment.ident == "self" || segment.ident == "super" {
+            return Err(Error::new_spanned(
+                segment,
+                "the bindings module must be given as a path from the crate root, e.g. `crate::stdb`",
+            ));
+        }
+        dir.push(segment.ident.to_string());
+    }
+    Ok(dir)
+}
+
+/// Lists the files of the bindings module ending with `suffix` (e.g. `_reducer.rs`), sorted by name.
+pub(crate) fn module_files(
+    module_dir: &FsPath,
+    suffix: &str,
+) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(module_dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(suffix));
+        if let Some(name) = name {
+            files.push((name.to_string(), path.clone()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parses a file of the bindings module.
+pub(crate) fn parse_module_file(file: &FsPath) -> syn::Result<syn::File> {
+    let span = Span::call_site();
+    let source = fs::read_to_string(file).map_err(|err| {
+        Error::new(
+            span,
+            format!("failed to read `{}`: {}", file.display(), err),
+        )
+    })?;
+    syn::parse_file(&source).map_err(|err| {
+        Error::new(
+            span,
+            format!("failed to parse `{}`: {}", file.display(), err),
+        )
+    })
+}
+
+/// Generates the event struct of a reducer from the `*Args` struct of its generated file,
+/// returns its name and definition.
+pub(crate) fn reducer_event(
+    module: &Path,
+    reducer: &str,
+    file: &FsPath,
+) -> syn::Result<(Ident, TokenStream)> {
+    let span = Span::call_site();
+    let ast = parse_module_file(file)?;
+
+    let mut resolver = PathResolver::new(module);
+    for item in &ast.items {
+        if let Item::Use(item_use) = item {
+            resolver.collect(&item_use.tree, &mut Vec::new());
+        }
+    }
+
+    let args = ast
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Struct(item_struct) if item_struct.ident.to_string().ends_with("Args") => {
+                Some(item_struct)
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Error::new(
+                span,
+                format!("no reducer arguments struct found in `{}`", file.display()),
+            )
+        })?;
+
+    let args_name = args.ident.to_string();
+    let struct_name = Ident::new(args_name.strip_suffix("Args").unwrap_or(&args_name), span);
+    let reducer_name = LitStr::new(reducer, span);
+    let doc = format!(" Sent when the reducer `{}` is invoked.", reducer);
+    let file_path = LitStr::new(&file.display().to_string(), span);
+
+    let fields = match &args.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                let mut ty = field.ty.clone();
+                resolver.visit_type_mut(&mut ty);
+                quote! { pub #ident: #ty, }
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let event = quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, bevy_spacetimedb::RegisterReducerEvent)]
+        #[stdb(module = #module, reducer = #reducer_name)]
+        pub struct #struct_name {
+            pub event: spacetimedb_sdk::ReducerEvent<#module::Reducer>,
+            #(#fields)*
+        }
+
+        // Recompile when the bindings are regenerated
+        const _: &[u8] = include_bytes!(#file_path);
+    };
+    Ok((struct_name, event))
+}
+
+/// Rewrites the paths of a generated file so that they can be used outside of the bindings module.
+pub(crate) struct PathResolver {
+    /// The names imported by the file, and the full path they refer to.
+    imports: HashMap<String, Path>,
+}
+
+impl PathResolver {
+    pub(crate) fn new(module: &Path) -> Self {
+        let mut imports = HashMap::new();
+        imports.insert("super".to_string(), module.clone());
+        Self { imports }
+    }
+
+    /// Collects the names imported by a `use` item.
+    pub(crate) fn collect(&mut self, tree: &UseTree, prefix: &mut Vec<Ident>) {
+        match tree {
+            UseTree::Path(use_path) => {
+                prefix.push(use_path.ident.clone());
+                self.collect(&use_path.tree, prefix);
+                prefix.pop();
+            }
+            UseTree::Name(use_name) if use_name.ident == "self" => {
+                if let Some(last) = prefix.last() {
+                    let path = self.resolve(prefix);
+                    self.imports.insert(last.to_string(), path);
+                }
+            }
+            UseTree::Name(use_name) => {
+                prefix.push(use_name.ident.clone());
+                let path = self.resolve(prefix);
+                prefix.pop();
+                self.imports.insert(use_name.ident.to_string(), path);
+            }
+            UseTree::Rename(use_rename) => {
+                let path = if use_rename.ident == "self" {
+                    self.resolve(prefix)
+                } else {
+                    prefix.push(use_rename.ident.clone());
+                    let path = self.resolve(prefix);
+                    prefix.pop();
+                    path
+                };
+                self.imports.insert(use_rename.rename.to_string(), path);
+            }
+            UseTree::Group(use_group) => {
+                for tree in &use_group.items {
+                    self.collect(tree, prefix);
+                }
+            }
+            UseTree::Glob(_) => {}
+        }
+    }
+
+    /// Turns the segments of an import into a path, resolving its first segment if it is itself imported.
+    fn resolve(&self, segments: &[Ident]) -> Path {
+        let mut path: Path = syn::parse_quote! { #(#segments)::* };
+        self.rewrite(&mut path);
+        path
+    }
+
+    /// Replaces the first segment of the path if it refers to an imported name.
+    fn rewrite(&self, path: &mut Path) {
+        if path.leading_colon.is_some() {
+            return;
+        }
+        let Some(first) = path.segments.first() else {
+            return;
+        };
+        let Some(import) = self.imports.get(&first.ident.to_string()) else {
+            return;
+        };
+
+        let mut segments = import.segments.clone();
+        if let Some(last) = segments.last_mut() {
+            last.arguments = first.arguments.clone();
+        }
+        segments.extend(path.segments.iter().skip(1).cloned());
+        path.leading_colon = import.leading_colon;
+        path.segments = segments;
+    }
+}
+
+impl VisitMut for PathResolver {
+    fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+        if type_path.qself.is_none() {
+            self.rewrite(&mut type_path.path);
+        }
+        visit_mut::visit_type_path_mut(self, type_path);
+    }
+}
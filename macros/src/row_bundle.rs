@@ -0,0 +1,148 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Path, Type};
+
+use crate::parse_ident;
+
+/// How a field of the bundle is built from the row.
+enum FieldSource {
+    /// Converted with `From` from a field of the row.
+    Field(Ident),
+    /// Returned by a function taking the row.
+    With(Path),
+}
+
+pub(crate) fn expand_row_bundle(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Type of the row, required
+    let mut row: Option<Type> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("stdb"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("row") {
+                row = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported stdb attribute, expected `row`"))
+            }
+        })?;
+    }
+    let Some(row) = row else {
+        return Err(Error::new_spanned(
+            struct_name,
+            "StdbRowBundle requires the type of the row, e.g. `#[stdb(row = Player)]`",
+        ));
+    };
+
+    let fields = match &input.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => &fields_named.named,
+            Fields::Unnamed(fields_unnamed) => {
+                return Err(Error::new_spanned(
+                    fields_unnamed,
+                    "StdbRowBundle requires named fields, tuple structs are not supported",
+                ));
+            }
+            Fields::Unit => {
+                return Err(Error::new_spanned(
+                    struct_name,
+                    "StdbRowBundle requires a struct with named fields",
+                ));
+            }
+        },
+        Data::Enum(data_enum) => {
+            return Err(Error::new_spanned(
+                data_enum.enum_token,
+                "StdbRowBundle can only be derived for structs",
+            ));
+        }
+        Data::Union(data_union) => {
+            return Err(Error::new_spanned(
+                data_union.union_token,
+                "StdbRowBundle can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut spawn_fields = Vec::new();
+    let mut updates = Vec::new();
+    for field in fields {
+        let Some(field_ident) = field.ident.as_ref() else {
+            return Err(Error::new_spanned(field, "field must have an identifier"));
+        };
+        let ty = &field.ty;
+        match field_source(field_ident, &field.attrs)? {
+            FieldSource::Field(row_field) => {
+                spawn_fields.push(quote! {
+                    #field_ident: <#ty as ::core::convert::From<_>>::from(
+                        ::core::clone::Clone::clone(&row.#row_field),
+                    )
+                });
+                // Only the components of the changed fields are inserted again
+                updates.push(quote! {
+                    if old.#row_field != new.#row_field {
+                        entity.insert(<#ty as ::core::convert::From<_>>::from(
+                            ::core::clone::Clone::clone(&new.#row_field),
+                        ));
+                    }
+                });
+            }
+            FieldSource::With(function) => {
+                spawn_fields.push(quote! { #field_ident: #function(row) });
+                updates.push(quote! {
+                    let component: #ty = #function(new);
+                    entity.insert(component);
+                });
+            }
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics bevy_spacetimedb::StdbRowBundle for #struct_name #ty_generics #where_clause {
+            type Row = #row;
+
+            fn spawn_bundle(row: &#row) -> impl bevy_spacetimedb::bevy::ecs::bundle::Bundle {
+                Self {
+                    #(#spawn_fields),*
+                }
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn apply_update(
+                old: &#row,
+                new: &#row,
+                mut entity: bevy_spacetimedb::bevy::ecs::system::EntityCommands,
+            ) {
+                #(#updates)*
+            }
+        }
+    })
+}
+
+/// Returns how a field is built from the row, from its `#[stdb(field = "...")]` or `#[stdb(with = ...)]` attribute.
+fn field_source(field_ident: &Ident, attrs: &[Attribute]) -> syn::Result<FieldSource> {
+    let mut source = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("stdb")) {
+        attr.parse_nested_meta(|meta| {
+            if source.is_some() {
+                return Err(meta.error("only one of `field` or `with` can be set"));
+            }
+            if meta.path.is_ident("field") {
+                let name: LitStr = meta.value()?.parse()?;
+                source = Some(FieldSource::Field(parse_ident(&name)?));
+                Ok(())
+            } else if meta.path.is_ident("with") {
+                source = Some(FieldSource::With(meta.value()?.parse()?));
+                Ok(())
+            } else {
+                Err(meta.error("unsupported stdb attribute, expected `field` or `with`"))
+            }
+        })?;
+    }
+    Ok(source.unwrap_or_else(|| FieldSource::Field(field_ident.clone())))
+}